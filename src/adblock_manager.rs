@@ -0,0 +1,105 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Compilation of the adblock filter lists, requests blocked by the adblocker and sites on which
+//! it is disabled.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::thread;
+
+use adblock::engine::Engine;
+use adblock::lists::FilterFormat;
+use relm::Channel;
+use tempfile::Builder as TempFileBuilder;
+use titanium_common::adblock::{
+    Whitelist,
+    engine_cache_content,
+    is_cache_stale,
+    list_paths,
+    load_rules,
+};
+
+use app::App;
+use config_dir::ConfigDir;
+use errors::{Error, Result};
+
+/// A request blocked by the adblocker.
+pub struct BlockedRequest {
+    pub rule: String,
+    pub url: String,
+}
+
+/// The requests blocked in a window, the most recent first.
+/// It is shared with the completer of the adblock log.
+pub type BlockedRequests = Rc<RefCell<VecDeque<BlockedRequest>>>;
+
+/// Create the whitelist of the sites on which the adblocker is disabled if its path can be created.
+pub fn create_adblock_whitelist(config_dir: &ConfigDir) -> Option<Whitelist> {
+    App::adblock_whitelist_path(config_dir).ok()
+        .map(Whitelist::new)
+}
+
+/// Check if the adblock engine cache needs to be compiled again.
+pub fn is_adblock_cache_stale(config_dir: &ConfigDir) -> Result<bool> {
+    let (user_lists_dir, subscriptions_dir) = App::adblock_lists_dirs(config_dir)?;
+    let cache_path = App::adblock_cache_path(config_dir)?;
+    Ok(is_cache_stale(&cache_path, &list_paths(&user_lists_dir, &subscriptions_dir)))
+}
+
+/// Compile the filter lists and serialize the engine in another thread, so that the web processes
+/// can load it quickly.
+/// The callback is called in the UI thread with the result of the compilation; the compilation is
+/// stopped if the returned channel is dropped.
+pub fn spawn_adblock_compilation<F>(config_dir: &ConfigDir, callback: F) -> Result<Channel<Result<()>>>
+    where F: FnMut(Result<()>) + 'static
+{
+    let (user_lists_dir, subscriptions_dir) = App::adblock_lists_dirs(config_dir)?;
+    let list_paths = list_paths(&user_lists_dir, &subscriptions_dir);
+    let cache_path = App::adblock_cache_path(config_dir)?;
+    let (channel, sender) = Channel::new(callback);
+    thread::spawn(move || {
+        let result = compile_engine(&list_paths, &cache_path);
+        if let Err(error) = sender.send(result) {
+            error!("Cannot send the adblock compilation result: {}", error);
+        }
+    });
+    Ok(channel)
+}
+
+/// Compile the rules of the filter lists and save the serialized engine at `cache_path`.
+fn compile_engine(list_paths: &[PathBuf], cache_path: &Path) -> Result<()> {
+    // Keep the text of the rules to be able to show which one blocked a page.
+    let engine = Engine::from_rules_debug(&load_rules(list_paths), FilterFormat::Standard);
+    let serialized = engine.serialize()
+        .map_err(|error| Error::from_string(format!("Cannot serialize the adblock engine: {:?}", error)))?;
+    // Write to a unique temporary file first to avoid having the web processes read a partial
+    // cache, even when two compilations run at the same time.
+    let directory = cache_path.parent()
+        .ok_or_else(|| Error::new("The adblock engine cache has no directory"))?;
+    let mut file = TempFileBuilder::new().tempfile_in(directory)?;
+    file.write_all(&engine_cache_content(list_paths, &serialized))?;
+    file.persist(cache_path).map_err(|error| error.error)?;
+    Ok(())
+}
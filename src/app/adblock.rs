@@ -19,135 +19,268 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
-use std::fs::{File, OpenOptions};
-use std::io::{
-    BufRead,
-    BufReader,
-    Read,
-    Write,
-};
+//! Manage the filter lists and the per-site whitelist of the adblocker.
+
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use mg::question;
 use webkit2gtk::{
     Download,
     DownloadExt,
-    URIResponseExt,
     WebViewExt,
 };
-use zip::ZipArchive;
 
+use titanium_common::adblock::SUBSCRIPTIONS_DIR;
+use titanium_common::InnerMessage::{AllowRequestOnce, ReloadAdblockLists, ReloadAdblockWhitelist};
+
+use adblock_manager::{BlockedRequest, spawn_adblock_compilation};
 use app::download::find_destination;
-use app::Msg::HostfileDownloaded;
+use app::Msg::{
+    AdblockListDownloaded,
+    AdblockListFailed,
+    AdblockListsCompiled,
+    AllowBlockedRequest,
+    BroadcastMessage,
+};
 use download_list_view::Msg::DownloadRemove;
 use errors::{Error, Result};
-use file;
+use html::encode_entities;
 use super::App;
-use urls::get_filename;
+use webview::Msg::AddCosmeticStylesheet;
 
-const WHITELIST: &[&str] = &[
-    "localhost",
-    "localhost.localdomain",
-    "broadcasthost",
-    "local",
-];
+/// The maximum number of blocked requests kept in the log of a window.
+const BLOCKED_REQUEST_LIMIT: usize = 1000;
 
 impl App {
-    pub fn adblock_update(&self) -> Result<()> {
-        let hostfile = self.model.config_dir.data_file("hosts")?;
-        let hostfile = hostfile.to_str()
-            .ok_or_else(|| Error::new("Cannot get hostfile"))?;
-        // Clear the current hosts file.
-        File::create(hostfile)?;
-
-        let urls = [
-            "https://www.malwaredomainlist.com/hostslist/hosts.txt",
-            "http://someonewhocares.org/hosts/hosts",
-            "http://winhelp2002.mvps.org/hosts.zip",
-            "http://malwaredomains.lehigh.edu/files/justdomains.zip",
-            "https://pgl.yoyo.org/adservers/serverlist.php?hostformat=hosts&mimetype=plaintext",
-        ];
-        for url in &urls {
+    /// Disable the adblocker on the current site.
+    pub fn adblock_disable_site(&mut self) -> Result<()> {
+        let url = self.model.current_url.clone();
+        let host =
+            match self.model.adblock_whitelist {
+                Some(ref mut adblock_whitelist) => adblock_whitelist.add(&url)?,
+                None => return Err(Error::new("Cannot find the adblock whitelist")),
+            };
+        match host {
+            Some(host) => {
+                self.adblock_whitelist_changed();
+                self.info(format!("Adblocker disabled on {}", host));
+            },
+            None => self.error("Cannot disable the adblocker on this page"),
+        }
+        Ok(())
+    }
+
+    /// Enable the adblocker on the current site.
+    pub fn adblock_enable_site(&mut self) -> Result<()> {
+        let url = self.model.current_url.clone();
+        let host =
+            match self.model.adblock_whitelist {
+                Some(ref mut adblock_whitelist) => adblock_whitelist.remove(&url)?,
+                None => return Err(Error::new("Cannot find the adblock whitelist")),
+            };
+        match host {
+            Some(host) => {
+                self.adblock_whitelist_changed();
+                self.info(format!("Adblocker enabled on {}", host));
+            },
+            None => self.info("The adblocker is already enabled on this site".to_string()),
+        }
+        Ok(())
+    }
+
+    /// Tell the web processes of every window to reload the whitelist and reload the current page
+    /// for the change to take effect.
+    fn adblock_whitelist_changed(&mut self) {
+        self.server_send(ReloadAdblockWhitelist());
+        self.model.relm.stream().emit(BroadcastMessage(ReloadAdblockWhitelist()));
+        self.widgets.webview.reload();
+    }
+
+    /// Subscribe to the filter list at the url, in the Adblock Plus format.
+    pub fn add_adblock_list(&mut self, args: &str) {
+        let args: Vec<_> = args.split_whitespace().collect();
+        if args.len() == 2 {
+            let name = args[0];
+            if name.contains('/') || name.starts_with('.') {
+                self.error(&format!("adblock-list: invalid list name {}", name));
+                return;
+            }
+            self.model.adblock_lists.insert(name.to_string(), args[1].to_string());
+        }
+        else {
+            self.error(&format!("adblock-list: expecting 2 arguments, got {} arguments", args.len()));
+        }
+    }
+
+    /// Add the stylesheet of the cosmetic filters sent by the web process for the page at `url`.
+    /// The stylesheet is ignored if another page was opened in the meantime.
+    pub fn add_cosmetic_stylesheet(&self, url: &str, css: String) {
+        if self.widgets.webview.uri().as_ref().map(|uri| uri.as_str()) == Some(url) {
+            self.components.webview.emit(AddCosmeticStylesheet(css));
+        }
+    }
+
+    /// Install the downloaded filter list and tell the web processes to reload the lists once all
+    /// of them are downloaded.
+    pub fn adblock_list_downloaded(&mut self, name: &str, filename: &str, download: Download) {
+        self.components.download_list_view.emit(DownloadRemove(download));
+        // The file does not exist if the download failed.
+        if Path::new(filename).exists() {
+            handle_error!(self.install_adblock_list(name, filename));
+        }
+        self.adblock_list_done();
+    }
+
+    /// Show the error of a filter list that could not be downloaded.
+    /// The finished signal is emitted after this one, so the list is marked as done then.
+    pub fn adblock_list_failed(&self, name: &str, error: &str) {
+        self.error(&format!("Cannot download the adblock list {}: {}", name, error));
+    }
+
+    // Mark one filter list as updated and compile the lists when it was the last one.
+    fn adblock_list_done(&mut self) {
+        self.model.adblock_lists_updating = self.model.adblock_lists_updating.saturating_sub(1);
+        if self.model.adblock_lists_updating == 0 {
+            handle_error!(self.compile_adblock_lists());
+        }
+    }
+
+    /// Tell the web processes to reload the filter lists, now that the engine cache is updated.
+    pub fn adblock_lists_compiled(&mut self, result: Result<()>) {
+        self.model.adblock_compilation = None;
+        // Even if the compilation failed, the web processes can still parse the new lists.
+        self.handle_error(result);
+        self.server_send(ReloadAdblockLists());
+        self.model.relm.stream().emit(BroadcastMessage(ReloadAdblockLists()));
+        self.info("Adblock lists updated".to_string());
+    }
+
+    /// Download the filter lists in the background.
+    pub fn adblock_update(&mut self) -> Result<()> {
+        if self.model.adblock_lists.is_empty() {
+            return Err(Error::new("No adblock list: add one with adblock-list <name> <url> in the config file"));
+        }
+        if self.model.adblock_lists_updating > 0 {
+            return Err(Error::new("The adblock lists are already being updated"));
+        }
+        self.remove_unsubscribed_adblock_lists();
+
+        // Find all the destinations before starting the downloads, so that an error does not leave
+        // some downloads running without being handled.
+        let mut downloads = vec![];
+        for (name, url) in &self.model.adblock_lists {
+            let destination = find_destination(&self.model.config_dir, &format!("{}.txt", name))?;
+            downloads.push((name, url, destination));
+        }
+
+        for (name, url, destination) in downloads {
             if let Some(download) = self.widgets.webview.download_uri(url) {
-                let suggested_filename =
-                    download.response()
-                        .and_then(|response| response.suggested_filename().map(Into::into))
-                        .unwrap_or_else(|| get_filename(url).unwrap_or_default());
-                if let Ok(destination) = find_destination(&self.model.config_dir, &suggested_filename) {
-                    download.set_destination(&destination);
-                    let destination = destination[7..].to_string(); // Remove file://
-                    let down = download.clone();
-                    connect!(self.model.relm, download, connect_finished(_),
-                        HostfileDownloaded(destination.clone(), down.clone()));
-                }
-                else {
-                    warn!("Cannot choose destination for file {}", suggested_filename);
-                }
+                download.set_destination(&destination);
+                let destination = destination[7..].to_string(); // Remove file://
+                let down = download.clone();
+                let list_name = name.clone();
+                connect!(self.model.relm, download, connect_failed(_, error),
+                    AdblockListFailed(list_name.clone(), error.to_string()));
+                let list_name = name.clone();
+                connect!(self.model.relm, download, connect_finished(_),
+                    AdblockListDownloaded(list_name.clone(), destination.clone(), down.clone()));
+                self.model.adblock_lists_updating += 1;
             }
             else {
                 warn!("Cannot download file {}", url);
             }
         }
 
+        self.info(format!("Updating {} adblock lists", self.model.adblock_lists_updating));
         Ok(())
     }
 
-    pub fn process_hostfile(&self, filename: &str, download: Download) -> Result<()> {
-        let just_domains = filename.contains("justdomains");
-        info!("Processing host file {}", filename);
-        let hostfile = self.model.config_dir.data_file("hosts")?;
-        let hostfile = hostfile.to_str()
-            .ok_or_else(|| Error::new("Cannot get hostfile"))?;
-        if filename.ends_with(".zip") {
-            let mut archive = ZipArchive::new(file::open(filename)?)?;
-            let mut file =
-                if filename.contains("/hosts") {
-                    archive.by_name("HOSTS")?
-                }
-                else if just_domains {
-                    archive.by_name("justdomains")?
-                }
-                else {
-                    return Err(Error::new(&format!("Unknown host zip file: {}", filename)));
-                };
-            copy_file(hostfile, just_domains, &mut file)?;
+    /// Handle the answer of the allow blocked page dialog.
+    /// If the answer is y (for yes), load the page without blocking it this time.
+    /// The page is still blocked if WebKit loads it in another web process than the current one,
+    /// since only the current one is told to allow it.
+    pub fn allow_blocked_request(&mut self, answer: Option<&str>, url: &str) {
+        if answer == Some("y") {
+            self.server_send(AllowRequestOnce(url.to_string()));
+            self.widgets.webview.load_uri(url);
         }
-        else {
-            let mut file = file::open(filename)?;
-            copy_file(hostfile, just_domains, &mut file)?;
-        }
-        self.components.download_list_view.emit(DownloadRemove(download));
+    }
+
+    // Compile the filter lists in another thread.
+    fn compile_adblock_lists(&mut self) -> Result<()> {
+        let stream = self.model.relm.stream().clone();
+        let channel = spawn_adblock_compilation(&self.model.config_dir,
+            move |result| stream.emit(AdblockListsCompiled(result)))?;
+        self.model.adblock_compilation = Some(channel);
         Ok(())
     }
-}
 
-fn copy_file<R: Read>(hostfile: &str, just_domains: bool, file: &mut R) -> Result<()> {
-    let mut buffer = [0; 4096];
-    let mut hostfile = OpenOptions::new()
-        .append(true)
-        .write(true)
-        .open(hostfile)?;
-    if just_domains {
-        while let Ok(size) = file.read(&mut buffer) {
-            if size == 0 {
-                break;
-            }
-            hostfile.write(&buffer[..size])?;
+    // Move the downloaded list to the adblock directory and save its update time.
+    fn install_adblock_list(&self, name: &str, filename: &str) -> Result<()> {
+        fs::rename(filename, App::adblock_list_path(&self.model.config_dir, name)?)?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        fs::write(App::adblock_list_timestamp_path(&self.model.config_dir, name)?, timestamp.to_string())?;
+        Ok(())
+    }
+
+    /// Log the request blocked by the adblocker.
+    /// If it is the main document, show an error page asking whether to allow it once.
+    pub fn request_blocked(&mut self, url: String, rule: String, is_document: bool) {
+        if is_document {
+            // Do not use the blocked url for the error page since its request would be blocked again.
+            self.widgets.webview.load_alternate_html(&blocked_page(&url, &rule), "about:blank", None);
+            let page_url = url.clone();
+            question(&self.streams.mg, &self.model.relm,
+                format!("This page was blocked by the adblock rule {}. Do you want to allow it once?", rule),
+                char_slice!['y', 'n'], move |answer| AllowBlockedRequest(answer, page_url.clone()));
         }
+        let mut blocked_requests = self.model.blocked_requests.borrow_mut();
+        blocked_requests.push_front(BlockedRequest {
+            rule,
+            url,
+        });
+        blocked_requests.truncate(BLOCKED_REQUEST_LIMIT);
     }
-    else {
-        let file = BufReader::new(file);
-        for line in file.lines() {
-            let mut line = line?;
-            if let Some(index) = line.chars().position(|c| c == '#') {
-                line.truncate(index);
-            }
-            let mut words = line.split_whitespace();
-            words.next();
-            if let Some(address) = words.next() {
-                if !WHITELIST.contains(&address) {
-                    writeln!(hostfile, "{}", address)?;
+
+    // Remove the lists that are not in the config file anymore.
+    fn remove_unsubscribed_adblock_lists(&self) {
+        let directory = match self.model.config_dir.data_file(SUBSCRIPTIONS_DIR) {
+            Ok(directory) => directory,
+            Err(_) => return,
+        };
+        if let Ok(entries) = fs::read_dir(directory) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                let is_subscribed = path.file_stem()
+                    .and_then(|name| name.to_str())
+                    .map(|name| self.model.adblock_lists.contains_key(name))
+                    .unwrap_or(true);
+                if !is_subscribed {
+                    if let Err(error) = fs::remove_file(&path) {
+                        warn!("Cannot remove {}: {}", path.display(), error);
+                    }
                 }
             }
         }
     }
-    Ok(())
+}
+
+/// Create the error page shown instead of a page blocked by the adblocker.
+fn blocked_page(url: &str, rule: &str) -> String {
+    format!("<!DOCTYPE html>
+<html>
+    <head>
+        <meta charset=\"utf-8\">
+        <title>Page blocked</title>
+    </head>
+    <body>
+        <h1>Page blocked by the adblocker</h1>
+        <p>The page <code>{}</code> was blocked by the rule <code>{}</code>.</p>
+        <p>Press <kbd>y</kbd> in the prompt to allow it once, or use <code>:adblock-disable-site</code> to disable the adblocker on this site.</p>
+    </body>
+</html>", encode_entities(url), encode_entities(rule))
 }
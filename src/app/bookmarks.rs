@@ -21,6 +21,8 @@
 
 //! Bookmark management in the application.
 
+use std::path::Path;
+
 use mg::{
     CustomDialog,
     DialogBuilder,
@@ -28,12 +30,29 @@ use mg::{
     Info,
     InputDialog,
 };
+use titanium_common::Percentage::{All, Percent};
 use webkit2gtk::WebViewExt;
 
 use app::{App, TAG_COMPLETER};
 use app::Msg::TagEdit;
+use bookmark_file;
 
 impl App {
+    /// Save the current page and its scroll position in the quickmark `mark`.
+    pub fn add_quickmark(&self, mark: char) {
+        if let Some(url) = self.widgets.webview.uri() {
+            let scroll_percentage =
+                match self.model.scroll_percentage {
+                    All => None,
+                    Percent(percent) => Some(percent),
+                };
+            match self.model.bookmark_manager.set_quickmark(mark, &url, scroll_percentage) {
+                Ok(()) => self.components.mg.emit(Info(format!("Added quickmark {}", mark))),
+                Err(err) => self.error(&err.to_string()),
+            }
+        }
+    }
+
     /// Add the current page to the bookmarks.
     pub fn bookmark(&self) {
         if let Some(url) = self.widgets.webview.uri() {
@@ -74,6 +93,52 @@ impl App {
         }
     }
 
+    /// Open the page saved in the quickmark `mark` and scroll to the saved position.
+    pub fn go_to_quickmark(&mut self, mark: char) {
+        match self.model.bookmark_manager.quickmark(mark) {
+            Ok(Some(quickmark)) => {
+                let is_current_page = self.widgets.webview.uri()
+                    .map(|url| url == quickmark.url)
+                    .unwrap_or(false);
+                if is_current_page {
+                    if let Some(percent) = quickmark.scroll_percentage {
+                        self.scroll_to(Some(percent as u32));
+                    }
+                }
+                else {
+                    // The scroll position is restored when the page finishes loading.
+                    self.model.scroll_percentage_to_restore = quickmark.scroll_percentage;
+                    self.open(&quickmark.url);
+                }
+            },
+            Ok(None) => self.error(&format!("Quickmark {} is not set", mark)),
+            Err(err) => self.error(&err.to_string()),
+        }
+    }
+
+    /// Export the bookmarks to the file at `path`.
+    pub fn export_bookmarks(&self, path: &str) {
+        let result = self.model.bookmark_manager.all()
+            .and_then(|bookmarks| {
+                bookmark_file::export(Path::new(path), &bookmarks)?;
+                Ok(bookmarks.len())
+            });
+        match result {
+            Ok(count) => self.components.mg.emit(Info(format!("Exported {} bookmarks to {}", count, path))),
+            Err(err) => self.error(&err.to_string()),
+        }
+    }
+
+    /// Import the bookmarks from the file at `path`.
+    pub fn import_bookmarks(&self, path: &str) {
+        let result = bookmark_file::import(Path::new(path))
+            .and_then(|bookmarks| self.model.bookmark_manager.import(&bookmarks));
+        match result {
+            Ok(count) => self.components.mg.emit(Info(format!("Imported {} bookmarks from {}", count, path))),
+            Err(err) => self.error(&err.to_string()),
+        }
+    }
+
     pub fn set_tags(&self, tags: Option<String>) {
         // Do not edit tags when the user press Escape.
         if let Some(tags) = tags {
@@ -25,6 +25,8 @@ use mg::Variables;
 use mg::DefaultConfig::{self, Dir, File};
 use webkit2gtk::WebViewExt;
 
+use titanium_common::adblock::SUBSCRIPTIONS_DIR;
+
 use config_dir::ConfigDir;
 use super::App;
 
@@ -44,20 +46,27 @@ pub fn default_config(config_dir: &ConfigDir) -> Vec<DefaultConfig> {
     let stylesheets_path = config_dir.config_file("stylesheets");
     let scripts_path = config_dir.config_file("scripts");
     let popups_path = config_dir.config_file("popups");
+    let sessions_path = config_dir.data_file("sessions");
+    let adblock_lists_path = config_dir.data_file(SUBSCRIPTIONS_DIR);
 
     let config_path = config_dir.config_file("config");
     let keys_path = config_dir.config_file("keys");
+    let marks_path = config_dir.config_file("marks");
     let webkit_config_path = config_dir.config_file("webkit");
     let hints_css_path = config_dir.config_file("stylesheets/hints.css");
     let (popup_whitelist_path, popup_blacklist_path) = App::popup_path(config_dir);
     let (permission_whitelist_path, permission_blacklist_path) = App::permission_path(config_dir);
+    let adblock_whitelist_path = App::adblock_whitelist_path(config_dir);
 
     vec![Dir(downloads_path),
          Dir(stylesheets_path),
          Dir(scripts_path),
          Dir(popups_path),
+         Dir(sessions_path),
+         Dir(adblock_lists_path),
          Dir(Ok(config_dir.data_home())),
          File(keys_path, include_str!("../../config/keys")),
+         File(marks_path, include_str!("../../config/marks")),
          File(config_path, include_str!("../../config/config")),
          File(webkit_config_path, include_str!("../../config/webkit")),
          File(hints_css_path, include_str!("../../config/stylesheets/hints.css")),
@@ -65,5 +74,6 @@ pub fn default_config(config_dir: &ConfigDir) -> Vec<DefaultConfig> {
          File(popup_blacklist_path, ""),
          File(permission_whitelist_path, ""),
          File(permission_blacklist_path, ""),
+         File(adblock_whitelist_path, ""),
         ]
 }
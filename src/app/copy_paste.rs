@@ -42,6 +42,19 @@ impl App {
         }
     }
 
+    /// Copy the text of an element in the system clipboard.
+    pub fn copy_text(&self, text: &str) {
+        let display = self.widgets.webview.display();
+        let clipboard = Clipboard::default(&display);
+        if let Some(clipboard) = clipboard {
+            clipboard.set_text(text);
+            self.info(format!("Copied text to clipboard: {}", text));
+        }
+        else {
+            self.error("Cannot get the system clipboard");
+        }
+    }
+
     /// Enter follow mode to copy the URL from a link to the system clipboard.
     pub fn copy_link_url(&mut self) {
         self.model.follow_mode = FollowMode::CopyLink;
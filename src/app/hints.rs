@@ -22,43 +22,67 @@
 //! Manage hints within the application.
 
 use gdk::EventKey;
+use gdk::keys::constants as key;
 
 use super::App;
+use message_server::Privacy;
 use webview::Msg::SetClickedURL;
 
 use titanium_common::Action::{
     self,
     CopyLink,
+    CopyText,
     DownloadLink,
     FileInput,
     GoInInsertMode,
     NoAction,
+    OpenInBackgroundWindow,
+    OpenInPrivateWindow,
+    OpenUrl,
 };
+use titanium_common::FollowMode;
 
 impl App {
     pub fn activate_action(&mut self, action: Action) {
         match action {
             CopyLink(url) => self.copy_link(&url),
+            CopyText(text) => self.copy_text(&text),
             DownloadLink(url) => self.download_link(&url),
             FileInput => self.show_file_input(),
             GoInInsertMode => self.go_in_insert_mode(),
             NoAction => (),
+            OpenInBackgroundWindow(url) => self.open_in_background_window(&url),
+            OpenInPrivateWindow(url) => self.open_in_new_window(&url, Privacy::Private),
+            OpenUrl(url) => self.open(&url),
         }
     }
 
     pub fn click_hint_element(&mut self, link: Option<String>) {
         self.components.webview.emit(SetClickedURL(link));
         self.activate_hint();
-        self.hide_hints();
+        // In rapid mode, stay in follow mode until the user leaves it.
+        if self.model.follow_mode != FollowMode::Rapid {
+            self.hide_hints();
+        }
+    }
+
+    /// Enter follow mode to apply the follow mode action on the selected element.
+    pub fn follow_with_mode(&mut self, follow_mode: FollowMode) {
+        self.model.follow_mode = follow_mode;
+        self.set_mode("follow");
+        self.follow_link();
     }
 
     /// In follow mode, send the key to the web process.
+    /// Uppercase characters are not part of the hint labels, so they are only used to filter the
+    /// hints by the text of the elements (the hint-text command filters with all the characters).
     pub fn handle_follow_key_press(&mut self, event_key: EventKey) {
-        if let Some(key_char) = event_key.keyval().to_unicode() {
+        if event_key.keyval() == key::BackSpace {
+            self.remove_hint_key();
+        }
+        else if let Some(key_char) = event_key.keyval().to_unicode() {
             if key_char.is_alphanumeric() {
-                if let Some(key_char) = key_char.to_lowercase().next() {
-                    self.enter_hint_key(key_char);
-                }
+                self.enter_hint_key(key_char);
             }
         }
     }
@@ -0,0 +1,102 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! History management in the application.
+
+use gio::Cancellable;
+use glib::TimeSpan;
+use mg::{DeleteCompletionItem, Info};
+use webkit2gtk::{
+    WebContextExt,
+    WebViewExt,
+    WebsiteDataManager,
+    WebsiteDataManagerExtManual,
+    WebsiteDataTypes,
+};
+
+use history::HistoryRange;
+use super::App;
+
+impl App {
+    /// Add the current page to the history.
+    /// Nothing is recorded for private windows.
+    pub fn add_history_visit(&self, url: &str) {
+        if !self.widgets.webview.is_ephemeral() {
+            let title = self.widgets.webview.title().map(Into::into);
+            handle_error!(self.model.history_manager.add_visit(url, title));
+        }
+    }
+
+    /// Update the title of the current page in the history, once it is loaded.
+    pub fn update_history_title(&self) {
+        if !self.widgets.webview.is_ephemeral() {
+            if let (Some(url), Some(title)) = (self.widgets.webview.uri(), self.widgets.webview.title()) {
+                if !title.is_empty() {
+                    handle_error!(self.model.history_manager.set_title(&url, &title));
+                }
+            }
+        }
+    }
+
+    /// Clear the history and the website data for the specified time range.
+    pub fn clear_history(&self, range: &str) {
+        let range =
+            match HistoryRange::parse(range) {
+                Ok(range) => range,
+                Err(error) => return self.error(&error.to_string()),
+            };
+        handle_error!(self.model.history_manager.clear(range));
+        let data_manager =
+            self.get_webview_context()
+                .and_then(|context| context.website_data_manager());
+        if let Some(data_manager) = data_manager {
+            clear_website_data(&data_manager, range);
+        }
+        self.components.mg.emit(Info("History cleared".to_string()));
+    }
+
+    /// Delete the history entry selected in completion.
+    pub fn delete_selected_history_entry(&self) {
+        let mut command = self.model.command_text.split_whitespace();
+        match command.next() {
+            Some("history") | Some("open") | Some("win-open") | Some("private-win-open") =>
+                if let Some(url) = command.next() {
+                    // Do not show message when deleting an history entry in completion.
+                    if let Err(err) = self.model.history_manager.delete(url) {
+                        self.error(&err.to_string());
+                    }
+                    self.components.mg.emit(DeleteCompletionItem);
+                },
+            _ => (),
+        }
+    }
+}
+
+/// Clear the website data (cache, cookies, storage, …) modified during the specified time range.
+fn clear_website_data(data_manager: &WebsiteDataManager, range: HistoryRange) {
+    // A time span of 0 clears all the data.
+    let time_span = TimeSpan::from_seconds(range.seconds().unwrap_or(0));
+    data_manager.clear(WebsiteDataTypes::ALL, time_span, None::<&Cancellable>, |result| {
+        if let Err(error) = result {
+            warn!("Cannot clear the website data: {}", error);
+        }
+    });
+}
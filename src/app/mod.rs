@@ -35,17 +35,19 @@ mod dialog;
 mod download;
 mod file_chooser;
 mod hints;
+mod history;
 mod pass_filler;
 mod paths;
 mod popup;
 mod search_engine;
 mod server;
+mod session;
 mod test_utils;
 mod url;
 pub mod user_agent;
 
-use std::cell::Cell;
-use std::collections::{BTreeSet, HashMap};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::rc::Rc;
 
 use gdk::{EventKey, Rectangle};
@@ -80,8 +82,9 @@ use mg::{
     question,
     yes_no_question, ForegroundColor,
 };
-use relm::{Relm, Widget};
+use relm::{Channel, Relm, Widget};
 use relm_derive::widget;
+use titanium_common::adblock::Whitelist;
 use titanium_common::protocol::decode;
 use webkit2gtk::{
     self,
@@ -99,20 +102,26 @@ use webkit2gtk::{
     WebContext,
     WebViewExt, UserMessageExt, WebContextExt,
 };
-use webkit2gtk::LoadEvent::{self, Started};
+use webkit2gtk::LoadEvent::{self, Finished, Started};
 use webkit2gtk::NavigationType::Other;
 
-use titanium_common::{FollowMode, InnerMessage, LAST_MARK};
+use titanium_common::{FollowMode, HintLabels, InnerMessage, LAST_MARK};
 use titanium_common::Percentage::{self, All, Percent};
 
+use adblock_manager::{BlockedRequests, create_adblock_whitelist};
 use bookmarks::BookmarkManager;
 use commands::AppCommand;
 use commands::AppCommand::*;
 use completers::{
+    AdblockLogCompleter,
     BookmarkCompleter,
+    ClosedWindowCompleter,
     FileCompleter,
+    HistoryCompleter,
+    SessionCompleter,
     TagCompleter,
     UserAgentCompleter,
+    UsernameCompleter,
 };
 use config_dir::ConfigDir;
 use download_list_view::DownloadListView;
@@ -121,7 +130,8 @@ use download_list_view::Msg::{
     DownloadListError,
 };
 use errors::Result;
-use message_server::Privacy;
+use history::HistoryManager;
+use message_server::{ClosedWindows, Privacy};
 use pass_manager::PasswordManager;
 use permission_manager::{Permission, PermissionManager, create_permission_manager};
 use popup_manager::{PopupManager, create_popup_manager};
@@ -130,11 +140,18 @@ use self::dialog::handle_script_dialog;
 use self::file_chooser::handle_file_chooser;
 use self::Msg::*;
 use self::user_agent::UserAgentManager;
-use settings::AppSettings;
+use session::WindowSession;
+use settings::{AppSettings, PasswordBackendKind};
 use settings::AppSettingsVariant::{
     self,
     HintChars,
+    HintMode,
     HomePage,
+    PasswordBackend,
+    PasswordKdbxFile,
+    PasswordKdbxPasswordCommand,
+    PasswordKdbxWrite,
+    SessionAutosave,
     WebkitUserAgent,
 };
 use urls::canonicalize_url;
@@ -168,8 +185,10 @@ use webview::Msg::{
 
 pub const APP_NAME: &'static str = env!("CARGO_PKG_NAME");
 const INIT_SCROLL_TEXT: &str = "[top]";
+const INSECURE_FORM_TEXT: &str = "[insecure form]";
 const TAG_COMPLETER: &str = "__tag";
 pub const USER_AGENT_COMPLETER: &str = "select-user-agent";
+const USERNAME_COMPLETER: &str = "__username";
 
 static MODES: Modes = &[
     Mode { name: "follow", prefix: "f", show_count: false },
@@ -177,7 +196,13 @@ static MODES: Modes = &[
 ];
 
 pub struct Model {
+    adblock_compilation: Option<Channel<Result<()>>>,
+    adblock_lists: HashMap<String, String>,
+    adblock_lists_updating: usize,
+    adblock_whitelist: Option<Whitelist>,
+    blocked_requests: BlockedRequests,
     bookmark_manager: BookmarkManager,
+    closed_windows: ClosedWindows,
     command_text: String,
     config_dir: ConfigDir,
     current_url: String,
@@ -185,52 +210,90 @@ pub struct Model {
     follow_mode: FollowMode,
     has_active_downloads: bool,
     hint_chars: String,
+    hint_labels: HintLabels,
+    history_manager: HistoryManager,
     home_page: Option<String>,
     in_follow_mode: Rc<Cell<bool>>,
+    init_session: Option<WindowSession>,
     init_url: Option<String>,
+    insecure_form: bool,
+    insecure_form_text: String,
     is_fullscreen: bool,
     mode: String,
     open_in_new_window: bool,
+    password_backend: PasswordBackendKind,
+    password_delete: Option<Channel<Result<()>>>,
+    password_insert: Option<Channel<Result<bool>>>,
+    password_kdbx_file: String,
+    password_kdbx_password_command: String,
+    password_kdbx_write: bool,
     password_manager: PasswordManager,
+    password_unlock: Option<Channel<Result<()>>>,
+    password_unlock_command: Option<AppCommand>,
     overridden_color: ForegroundColor,
     permission_manager: Option<PermissionManager>,
     popup_manager: Option<PopupManager>,
     previous_opened_urls: BTreeSet<String>,
     relm: Relm<App>,
+    scroll_percentage: Percentage,
+    scroll_percentage_to_restore: Option<i64>,
     scroll_text: String,
     search_engines: HashMap<String, String>,
+    search_jump_added: bool,
+    session_autosave: bool,
+    session_autosave_pending: bool,
     title: String,
     user_agents: HashMap<String, String>,
     user_agent_manager: UserAgentManager,
+    usernames: Rc<RefCell<Vec<String>>>,
     web_context: WebContext,
 }
 
 #[derive(Msg)]
 pub enum Msg {
+    AdblockListDownloaded(String, String, Download),
+    AdblockListFailed(String, String),
+    AdblockListsCompiled(Result<()>),
+    AllowBlockedRequest(Option<String>, String),
     AppSetMode(String),
     AppSettingChanged(AppSettingsVariant),
     AskPermission(webkit2gtk::PermissionRequest),
+    AutosaveWindowSession,
+    BroadcastMessage(InnerMessage),
     ChangeUrl(String, String),
     Create(NavigationAction),
     Command(AppCommand),
     CommandText(String),
+    CreateBackgroundWindow(String, Privacy),
     CreateWindow(String, Privacy),
     DecideDownloadDestination(Download, String),
     DownloadDestination(DialogResult, Download, String),
     Exit(bool),
     FileDialogSelection(Option<String>),
     HasActiveDownloads(bool),
-    HostfileDownloaded(String, Download),
     InsecureContent,
+    InsecureFormAnswer(bool, bool),
     KeyPress(EventKey),
     LoadChanged(LoadEvent),
+    LoadSession(String),
     MessageRecv(InnerMessage),
     MouseTargetChanged(HitTestResult),
     OverwriteDownload(Download, String, bool),
+    PasswordBackendUnlocked(Result<()>),
+    PasswordDeleted(Result<()>),
+    PasswordSaved(Result<bool>),
+    PasswordUsernameLoad(Option<String>),
+    PasswordUsernameOtp(Option<String>),
+    PasswordUsernameSubmit(Option<String>),
     PermissionResponse(webkit2gtk::PermissionRequest, Option<String>),
     PopupDecision(Option<String>, String),
     Remove(String),
+    ReopenWindow(Option<String>),
+    SaveSession(String),
+    SendMessage(InnerMessage),
+    SendWindowSession,
     ShowError(String),
+    ShowInfo(String),
     ShowZoom(i32),
     TagEdit(Option<String>),
     TitleChanged,
@@ -239,6 +302,9 @@ pub enum Msg {
     WebProcessCrashed,
     WebViewClose,
     WebViewFullscreen(bool),
+    WindowClosed(WindowSession, Privacy),
+    WindowSessionChanged(WindowSession),
+    WindowSessionSent(Option<WindowSession>),
 }
 
 #[widget]
@@ -250,6 +316,9 @@ impl Widget for App {
     fn handle_load_changed(&mut self, load_event: LoadEvent) {
         if load_event == Started {
             self.model.overridden_color = ForegroundColor::None;
+            self.model.insecure_form = false;
+            self.model.insecure_form_text = String::new();
+            self.model.scroll_percentage = Percent(0);
             self.model.scroll_text = INIT_SCROLL_TEXT.to_string();
             self.components.webview.emit(EndSearch);
             self.components.webview.emit(AddStylesheets);
@@ -267,6 +336,14 @@ impl Widget for App {
                     self.model.overridden_color = ForegroundColor::Red;
                 }
             }
+            if load_event == Finished {
+                self.update_history_title();
+                // Restore the scroll position of the quickmark or session that was just opened.
+                if let Some(percent) = self.model.scroll_percentage_to_restore.take() {
+                    self.scroll_to(Some(percent as u32));
+                }
+                self.schedule_window_session_autosave();
+            }
         }
     }
 
@@ -278,7 +355,16 @@ impl Widget for App {
             Err(error) => self.error(&error.to_string()),
         }
 
+        match App::history_path(&self.model.config_dir) {
+            Ok(history_path) => {
+                handle_error!(self.model.history_manager.connect(history_path));
+                handle_error!(self.model.history_manager.create_tables());
+            },
+            Err(error) => self.error(&error.to_string()),
+        }
+
         handle_error!(self.clean_download_folder());
+        self.init_adblock_whitelist();
         self.init_permission_manager();
         self.init_popup_manager();
         self.open_init_url();
@@ -294,11 +380,25 @@ impl Widget for App {
         }
     }
 
-    fn model(relm: &Relm<Self>, (init_url, config_dir, web_context, previous_opened_urls): (Option<String>, ConfigDir, WebContext, BTreeSet<String>)) -> Model {
+    /// Warn that the page contains a password or credit card input while not using HTTPS.
+    fn insecure_form_detected(&mut self) {
+        self.model.insecure_form = true;
+        self.model.insecure_form_text = INSECURE_FORM_TEXT.to_string();
+    }
+
+    fn model(relm: &Relm<Self>, (init_url, config_dir, web_context, previous_opened_urls, init_session, closed_windows):
+        (Option<String>, ConfigDir, WebContext, BTreeSet<String>, Option<WindowSession>, ClosedWindows)) -> Model {
+        let adblock_whitelist = create_adblock_whitelist(&config_dir);
         let permission_manager = create_permission_manager(&config_dir);
         let popup_manager = create_popup_manager(&config_dir);
         Model {
+            adblock_compilation: None,
+            adblock_lists: HashMap::new(),
+            adblock_lists_updating: 0,
+            adblock_whitelist,
+            blocked_requests: Rc::new(RefCell::new(VecDeque::new())),
             bookmark_manager: BookmarkManager::new(),
+            closed_windows,
             command_text: String::new(),
             config_dir,
             current_url: String::new(),
@@ -306,29 +406,51 @@ impl Widget for App {
             follow_mode: FollowMode::Click,
             has_active_downloads: false,
             hint_chars: "hjklasdfgyuiopqwertnmzxcvb".to_string(),
+            hint_labels: HintLabels::Letters,
+            history_manager: HistoryManager::new(),
             home_page: None,
             in_follow_mode: Rc::new(Cell::new(false)),
+            init_session,
             init_url,
+            insecure_form: false,
+            insecure_form_text: String::new(),
             is_fullscreen: false,
             mode: "normal".to_string(),
             open_in_new_window: false,
-            password_manager: PasswordManager::new(),
+            password_backend: PasswordBackendKind::Pass,
+            password_delete: None,
+            password_insert: None,
+            password_kdbx_file: String::new(),
+            password_kdbx_password_command: String::new(),
+            password_kdbx_write: false,
+            password_manager: PasswordManager::new(App::last_usernames_path(&config_dir).ok()),
+            password_unlock: None,
+            password_unlock_command: None,
             overridden_color: ForegroundColor::None,
             permission_manager,
             popup_manager,
             previous_opened_urls,
             relm: relm.clone(),
+            scroll_percentage: Percent(0),
+            scroll_percentage_to_restore: None,
             scroll_text: INIT_SCROLL_TEXT.to_string(),
             search_engines: HashMap::new(),
+            search_jump_added: false,
+            session_autosave: false,
+            session_autosave_pending: false,
             title: APP_NAME.to_string(),
             user_agents: HashMap::new(),
             user_agent_manager: UserAgentManager,
+            usernames: Rc::new(RefCell::new(vec![])),
             web_context,
         }
     }
 
     fn open_init_url(&mut self) {
-        if let Some(ref url) = self.model.init_url {
+        if let Some(session) = self.model.init_session.take() {
+            self.restore_window_session(session);
+        }
+        else if let Some(ref url) = self.model.init_url {
             // Open as a file if the path exist, otherwise open as a normal URL.
             let url = canonicalize_url(url);
             self.components.webview.emit(PageOpen(url));
@@ -357,6 +479,7 @@ impl Widget for App {
 
     /// Show the scroll percentage.
     fn show_scroll(&mut self, scroll_percentage: Percentage) {
+        self.model.scroll_percentage = scroll_percentage;
         self.model.scroll_text =
             match scroll_percentage {
                 All => "[all]".to_string(),
@@ -364,16 +487,27 @@ impl Widget for App {
                 Percent(100) => "[bot]".to_string(),
                 Percent(percent) => format!("[{}%]", percent),
             };
+        self.schedule_window_session_autosave();
     }
 
     fn update(&mut self, event: Msg) {
         match event {
+            AdblockListDownloaded(name, file, download) => self.adblock_list_downloaded(&name, &file, download),
+            AdblockListFailed(name, error) => self.adblock_list_failed(&name, &error),
+            AdblockListsCompiled(result) => self.adblock_lists_compiled(result),
+            AllowBlockedRequest(answer, url) =>
+                self.allow_blocked_request(answer.as_ref().map(|str| str.as_str()), &url),
             AppSetMode(mode) => {
                 self.adjust_in_follow_mode(&mode);
-                self.model.mode = mode
+                self.model.mode = mode;
+                self.model.search_jump_added = false;
             },
             AppSettingChanged(setting) => self.setting_changed(setting),
             AskPermission(request) => self.handle_permission_request(&request),
+            AutosaveWindowSession => {
+                self.model.session_autosave_pending = false;
+                self.autosave_window_session();
+            },
             Create(navigation_action) => self.handle_create(navigation_action),
             Command(ref command) => self.handle_command(command),
             CommandText(text) => self.model.command_text = text,
@@ -384,19 +518,33 @@ impl Widget for App {
             Exit(can_quit) => self.quit(can_quit),
             FileDialogSelection(file) => self.file_dialog_selection(file),
             HasActiveDownloads(active) => self.model.has_active_downloads = active,
-            HostfileDownloaded(file, download) => handle_error!(self.process_hostfile(&file, download)),
             InsecureContent => self.insecure_content_detected(),
+            InsecureFormAnswer(submit, answer) =>
+                if answer {
+                    handle_error!(self.select_username(submit))
+                },
             KeyPress(event_key) => self.handle_key_press(event_key),
             LoadChanged(load_event) => self.handle_load_changed(load_event),
             MessageRecv(message) => self.message_recv(message),
             MouseTargetChanged(hit_test_result) => self.mouse_target_changed(hit_test_result),
             // To be listened by the user.
+            CreateBackgroundWindow(_, _) => (),
+            // To be listened by the user.
             CreateWindow(_, _) => (),
             OverwriteDownload(download, download_destination, overwrite) =>
                 self.overwrite_download(download, download_destination, overwrite),
+            PasswordBackendUnlocked(result) => self.password_backend_unlocked(result),
+            PasswordDeleted(result) => self.password_deleted(result),
+            PasswordSaved(result) => self.password_saved(result),
+            PasswordUsernameLoad(username) => handle_error!(self.load_username_password(username, false)),
+            PasswordUsernameOtp(username) => handle_error!(self.insert_username_otp(username)),
+            PasswordUsernameSubmit(username) => handle_error!(self.load_username_password(username, true)),
             PopupDecision(answer, url) => self.handle_answer(answer.as_ref().map(|str| str.as_str()), &url),
             PermissionResponse(request, choice) => self.handle_permission_response(&request, choice),
+            SendMessage(message) => self.server_send(message),
+            SendWindowSession => self.send_window_session(),
             ShowError(error) => self.error(&error),
+            ShowInfo(info) => self.info(info),
             ShowZoom(level) => self.show_zoom(level),
             TagEdit(tags) => self.set_tags(tags),
             TitleChanged => self.set_title(),
@@ -407,7 +555,8 @@ impl Widget for App {
             WebViewFullscreen(fullscreen) => self.model.is_fullscreen = fullscreen,
 
             // To be listened by the user.
-            ChangeUrl(_, _) | Remove(_) => (),
+            BroadcastMessage(_) | ChangeUrl(_, _) | LoadSession(_) | Remove(_) | ReopenWindow(_) | SaveSession(_) |
+                WindowClosed(_, _) | WindowSessionChanged(_) | WindowSessionSent(_) => (),
         }
     }
 
@@ -416,6 +565,7 @@ impl Widget for App {
         if let Some(url) = self.widgets.webview.uri() {
             let url = url.to_string();
             self.model.relm.stream().emit(ChangeUrl(self.model.current_url.clone(), url.clone()));
+            self.add_history_visit(&url);
             self.model.current_url = url;
         }
     }
@@ -426,12 +576,18 @@ impl Widget for App {
             Some(self.model.config_dir.config_home()), default_config(&self.model.config_dir))
         {
             Completers: hash! {
+                "adblock-log" => Box::new(AdblockLogCompleter::new(self.model.blocked_requests.clone())),
                 "file" => Box::new(FileCompleter::new()),
+                "history" => Box::new(HistoryCompleter::new()),
                 "open" => Box::new(BookmarkCompleter::new("open")),
                 "win-open" => Box::new(BookmarkCompleter::new("win-open")),
                 "private-win-open" => Box::new(BookmarkCompleter::new("private-win-open")),
+                "session-delete" => Box::new(SessionCompleter::new("session-delete", self.model.config_dir.clone())),
+                "session-load" => Box::new(SessionCompleter::new("session-load", self.model.config_dir.clone())),
+                "undo-close-select" => Box::new(ClosedWindowCompleter::new(self.model.closed_windows.clone())),
                 TAG_COMPLETER => Box::new(TagCompleter::new()),
                 USER_AGENT_COMPLETER => Box::new(UserAgentCompleter::new()),
+                USERNAME_COMPLETER => Box::new(UsernameCompleter::new(self.model.usernames.clone())),
             },
             //DarkTheme: true, // TODO: reenable when the webkit2gtk issue with dark theme is fixed.
             Title: self.model.title.clone(),
@@ -464,6 +620,10 @@ impl Widget for App {
                     web_process_crashed => (WebProcessCrashed, false),
                 },
             },
+            StatusBarItem {
+                Color: ForegroundColor::Red,
+                Text: self.model.insecure_form_text.clone(),
+            },
             #[name="scroll_label"]
             StatusBarItem {
                 Text: self.model.scroll_text.clone(),
@@ -497,8 +657,14 @@ fn message_recv(msg: &UserMessage) -> Option<Msg> {
 impl App {
     fn add_mark(&mut self, mark: &str) {
         let mark = mark_from_str(mark);
-        self.server_send(InnerMessage::Mark(mark));
-        self.components.mg.emit(Info(format!("Added mark {}", mark as char)));
+        // The uppercase marks are global and saved across sessions.
+        if mark.is_ascii_uppercase() {
+            self.add_quickmark(mark as char);
+        }
+        else {
+            self.server_send(InnerMessage::Mark(mark));
+            self.components.mg.emit(Info(format!("Added mark {}", mark as char)));
+        }
     }
 
     fn add_user_agent(&mut self, user_agent: &str) {
@@ -514,6 +680,18 @@ impl App {
     }
 
     fn close_webview(&self) {
+        // Save the latest state (e.g. scroll position) of the window before it is closed.
+        self.autosave_window_session();
+        if let Some(state) = self.window_state() {
+            let privacy =
+                if self.widgets.webview.is_ephemeral() {
+                    Privacy::Private
+                }
+                else {
+                    Privacy::Normal
+                };
+            self.model.relm.stream().emit(WindowClosed(state, privacy));
+        }
         self.model.relm.stream().emit(Remove(self.model.current_url.clone()));
 
         self.components.mg.emit(CloseWin);
@@ -593,48 +771,75 @@ impl App {
 
     fn go_to_mark(&mut self, mark: &str) {
         let mark = mark_from_str(mark);
-        self.server_send(InnerMessage::GoToMark(mark));
+        if mark.is_ascii_uppercase() {
+            self.go_to_quickmark(mark as char);
+        }
+        else {
+            self.server_send(InnerMessage::GoToMark(mark));
+        }
     }
 
     /// Handle the command.
     fn handle_command(&mut self, command: &AppCommand) {
         match *command {
             ActivateSelection => self.activate_selection(),
+            AdblockDisableSite => handle_error!(self.adblock_disable_site()),
+            AdblockEnableSite => handle_error!(self.adblock_enable_site()),
+            AdblockList(ref args) => self.add_adblock_list(args),
+            AdblockLog(ref url) => self.copy_link(url),
             AdblockUpdate => handle_error!(self.adblock_update()),
             AddUserAgent(ref user_agent) => self.add_user_agent(user_agent),
             Back => self.history_back(),
             BackwardSearch(ref input) => {
+                self.add_search_jump();
                 self.components.webview.emit(SearchBackward(true));
                 self.components.webview.emit(PageSearch(input.clone()));
             },
             Bookmark => self.bookmark(),
             BookmarkDel => self.delete_bookmark(),
             BookmarkEditTags => self.edit_bookmark_tags(),
+            BookmarkExport(ref path) => self.export_bookmarks(path),
+            BookmarkImport(ref path) => self.import_bookmarks(path),
             ClearCache => self.clear_cache(),
             ClickNextPage => self.click_next_page(),
             ClickPrevPage => self.click_prev_page(),
+            CopyAnchorUrl => self.follow_with_mode(FollowMode::CopyAnchor),
             CopyLinkUrl => self.copy_link_url(),
+            CopyText => self.follow_with_mode(FollowMode::CopyText),
             CopyUrl => self.copy_current_url(),
             DeleteAllCookies => self.delete_all_cookies(),
             DeleteCookies(ref domain) => self.delete_cookies(domain),
             DeleteSelectedBookmark => self.delete_selected_bookmark(),
             FinishSearch => self.components.webview.emit(PageFinishSearch),
+            FocusElement => self.follow_with_mode(FollowMode::Focus),
             FocusInput => self.focus_input(),
             Follow => self.follow(),
             Forward => self.history_forward(),
+            GoAnchor => self.follow_with_mode(FollowMode::Anchor),
             GoMark(ref mark) => self.go_to_mark(mark),
             GoParentDir(parent_level) => self.go_parent_directory(parent_level),
             GoRootDir => self.go_root_directory(),
             HideHints => self.hide_hints(),
+            HintText => self.toggle_hint_text(),
+            History(ref url) => self.open(url),
+            HistoryClear(ref range) => self.clear_history(range),
+            HistoryDelete => self.delete_selected_history_entry(),
             Hover => self.hover(),
             Insert => self.go_in_insert_mode(),
             Inspector => self.components.webview.emit(ShowInspector),
+            JumpBackward => self.jump_backward(),
+            JumpForward => self.jump_forward(),
             KillWin => self.close_webview(),
             Mark(ref mark) => self.add_mark(mark),
             Normal => self.go_in_normal_mode(),
             Open(ref url) => self.open(url),
+            // Unlock the password backend before running the commands using it.
+            PasswordDelete | PasswordGenerate(_) | PasswordInsert | PasswordInsertOtp | PasswordInsertSubmit |
+                PasswordLoad | PasswordSave | PasswordSubmit if !self.password_backend_ready(command) => (),
             PasswordDelete => handle_error!(self.delete_password()),
+            PasswordGenerate(ref args) => handle_error!(self.generate_password(args)),
             PasswordInsert => handle_error!(self.insert_password()),
+            PasswordInsertOtp => handle_error!(self.insert_otp()),
             PasswordInsertSubmit => handle_error!(self.insert_password_submit()),
             PasswordLoad => handle_error!(self.load_password()),
             PasswordSave => self.save_password(),
@@ -642,11 +847,14 @@ impl App {
             PasteUrl => self.paste_url(),
             PreferredLanguage(ref language) => self.model.web_context.set_preferred_languages(&[&language]),
             Print => self.components.webview.emit(PagePrint),
+            PrivateWinFollow => self.follow_with_mode(FollowMode::PrivateWindow),
             PrivateWinOpen(ref url) => self.open_in_new_window(url, Privacy::Private),
             Quit => self.try_quit(),
+            RapidFollow => self.follow_with_mode(FollowMode::Rapid),
             Reload => self.widgets.webview.reload(),
             ReloadBypassCache => self.widgets.webview.reload_bypass_cache(),
             RestoreUrls => self.restore_urls(),
+            SaveImage => self.follow_with_mode(FollowMode::DownloadImage),
             SaveLink => self.save_link(),
             Screenshot(ref path) => self.components.webview.emit(PageScreenshot(path.clone())),
             ScrollDown => self.scroll_down_page(),
@@ -655,19 +863,27 @@ impl App {
             ScrollLeft => self.scroll_left(),
             ScrollRight => self.scroll_right(),
             ScrollTo(percent) => self.scroll_to(percent),
+            ScrollToElement => self.follow_with_mode(FollowMode::ScrollIntoView),
             ScrollTop => self.scroll_top(),
             ScrollUp => self.scroll_up_page(),
             ScrollUpHalf => self.scroll_up_half_page(),
             ScrollUpLine => self.scroll_up_line(),
             Search(ref input) => {
+                self.add_search_jump();
                 self.components.webview.emit(SearchBackward(false));
                 self.components.webview.emit(PageSearch(input.clone()));
             },
             SearchEngine(ref args) => self.add_search_engine(args),
             SearchNext => self.components.webview.emit(PageSearchNext),
             SearchPrevious => self.components.webview.emit(PageSearchPrevious),
+            SelectText => self.follow_with_mode(FollowMode::Select),
             SelectUserAgent(ref name) => self.select_user_agent(name),
+            SessionDelete(ref name) => self.delete_session(name),
+            SessionLoad(ref name) => self.model.relm.stream().emit(LoadSession(name.clone())),
+            SessionSave(ref name) => self.model.relm.stream().emit(SaveSession(name.clone())),
             Stop => self.widgets.webview.stop_loading(),
+            UndoClose => self.model.relm.stream().emit(ReopenWindow(None)),
+            UndoCloseSelect(ref url) => self.model.relm.stream().emit(ReopenWindow(Some(url.clone()))),
             UrlIncrement => self.url_increment(),
             UrlDecrement => self.url_decrement(),
             WinFollow => self.win_follow(),
@@ -807,6 +1023,17 @@ impl App {
         self.components.mg.emit(Info(info));
     }
 
+    fn init_adblock_whitelist(&mut self) {
+        let result =
+            if let Some(ref mut adblock_whitelist) = self.model.adblock_whitelist {
+                adblock_whitelist.load().map_err(Into::into)
+            }
+            else {
+                Ok(())
+            };
+        self.handle_error(result);
+    }
+
     fn init_permission_manager(&mut self) {
         let result =
             if let Some(ref mut permission_manager) = self.model.permission_manager {
@@ -877,6 +1104,24 @@ impl App {
     fn setting_changed(&mut self, setting: AppSettingsVariant) {
         match setting {
             HintChars(chars) => self.model.hint_chars = chars,
+            HintMode(mode) => self.model.hint_labels = mode.to_hint_labels(),
+            SessionAutosave(autosave) => self.model.session_autosave = autosave,
+            PasswordBackend(backend) => {
+                self.model.password_backend = backend;
+                self.update_password_backend();
+            },
+            PasswordKdbxFile(path) => {
+                self.model.password_kdbx_file = path;
+                self.update_password_backend();
+            },
+            PasswordKdbxPasswordCommand(command) => {
+                self.model.password_kdbx_password_command = command;
+                self.update_password_backend();
+            },
+            PasswordKdbxWrite(write) => {
+                self.model.password_kdbx_write = write;
+                self.update_password_backend();
+            },
             HomePage(url) => {
                 if  self.model.init_url.is_none() {
                     self.components.webview.emit(PageOpen(url.clone()));
@@ -888,8 +1133,9 @@ impl App {
     }
 
     /// Show the zoom level in the status bar.
-    fn show_zoom(&self, level: i32) {
+    fn show_zoom(&mut self, level: i32) {
         self.info(format!("Zoom level: {}%", level));
+        self.schedule_window_session_autosave();
     }
 
     /// Try to close the web view and quit the application.
@@ -19,29 +19,118 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
+use mg::{CustomDialog, DialogBuilder, InputDialog, yes_no_question};
+use relm::Channel;
 use titanium_common::InnerMessage::{
     GetCredentials,
     InsertText,
+    LoadGeneratedPassword,
     LoadUsernamePass,
     SubmitLoginForm,
 };
 
-use super::App;
+use super::{App, USERNAME_COMPLETER};
+use super::Msg::{
+    self,
+    InsecureFormAnswer,
+    PasswordBackendUnlocked,
+    PasswordDeleted,
+    PasswordSaved,
+    PasswordUsernameLoad,
+    PasswordUsernameOtp,
+    PasswordUsernameSubmit,
+};
 
-use errors::Result;
+use commands::AppCommand;
+use errors::{Error, Result};
+use pass_manager::{KdbxBackend, PassBackend, PasswordBackend, generate_password};
+use settings::PasswordBackendKind;
+use totp::Totp;
 
 impl App {
+    /// Ask which username to use if there are many for the current URL, then send the message
+    /// created by `callback` with it.
+    fn ask_username(&mut self, callback: fn(Option<String>) -> Msg) -> Result<()> {
+        let mut usernames = self.model.password_manager.get_usernames(&self.model.current_url)?;
+        if usernames.len() > 1 {
+            // The last username used on this page comes first.
+            let default_answer = usernames[0].clone();
+            *self.model.usernames.borrow_mut() = usernames;
+            let responder = Box::new(InputDialog::new(&self.model.relm, callback));
+            let builder = DialogBuilder::new()
+                .completer(USERNAME_COMPLETER)
+                .default_answer(default_answer)
+                .message("Username:".to_string())
+                .responder(responder);
+            self.components.mg.emit(CustomDialog(builder));
+            Ok(())
+        }
+        else if let Some(username) = usernames.pop() {
+            self.model.relm.stream().emit(callback(Some(username)));
+            Ok(())
+        }
+        else {
+            self.info("No password for the current URL".to_string());
+            Ok(())
+        }
+    }
+
+    /// Ask for a confirmation before loading the credentials in a page not using HTTPS.
+    fn confirm_insecure_form(&mut self, submit: bool) -> Result<()> {
+        if self.model.insecure_form {
+            yes_no_question(&self.streams.mg, &self.model.relm,
+                "This page is not using HTTPS. Do you really want to load the password?".to_string(),
+                move |answer| InsecureFormAnswer(submit, answer));
+            Ok(())
+        }
+        else {
+            self.select_username(submit)
+        }
+    }
+
     /// Delete the password for the current URL.
-    pub fn delete_password(&self) -> Result<()> {
+    pub fn delete_password(&mut self) -> Result<()> {
+        if self.model.password_delete.is_some() {
+            return Err(Error::new("A password is already being deleted"));
+        }
         let usernames = self.model.password_manager.get_usernames(&self.model.current_url)?;
         if !usernames.is_empty() {
             // TODO: ask for which username to delete.
             let username = &usernames[0];
-            self.model.password_manager.delete(&self.model.current_url, username)?;
+            let stream = self.model.relm.stream().clone();
+            let (channel, sender) = Channel::new(move |result| stream.emit(PasswordDeleted(result)));
+            self.model.password_manager.delete(&self.model.current_url, username, sender)?;
+            self.model.password_delete = Some(channel);
+        }
+        else {
+            self.info("No password for the current URL".to_string());
+        }
+        Ok(())
+    }
+
+    /// Generate a password, write it in the password inputs of the form and save it with the
+    /// username of the form.
+    pub fn generate_password(&mut self, args: &Option<String>) -> Result<()> {
+        let password = generate_password(args.as_ref().map(String::as_str).unwrap_or(""))?;
+        // The web process answers with the credentials, which are then saved.
+        self.server_send(LoadGeneratedPassword(password));
+        Ok(())
+    }
+
+    /// Insert the one-time password of the current URL in the focused text input.
+    /// If multiple credentials exist, ask the user which one to use.
+    pub fn insert_otp(&mut self) -> Result<()> {
+        self.ask_username(PasswordUsernameOtp)
+    }
+
+    /// Insert the one-time password of `username` in the focused text input.
+    pub fn insert_username_otp(&mut self, username: Option<String>) -> Result<()> {
+        // Do nothing when the user press Escape.
+        if let Some(username) = username {
+            let uri = self.model.password_manager.get_otp_uri(&self.model.current_url, &username)?;
+            let code = Totp::from_uri(&uri)?.generate();
+            self.server_send(InsertText(code));
         }
-        /*Ok(true) => self.app.info("Password deleted"),
-          Ok(false) => self.app.info("No password for the current URL"),
-          Err(err) => self.error(err.to_string()),*/
         Ok(())
     }
 
@@ -66,40 +155,131 @@ impl App {
 
     /// Load the username and password in the login form.
     /// If multiple credentials exist, ask the user which one to use.
-    /// Return true if a login form was filled.
     pub fn load_password(&mut self) -> Result<()> {
-        /*Ok(true) => return Ok(true),
-          Ok(false) => self.app.info("No password for the current URL"),
-          Err(err) => self.error(err.to_string()),*/
-        let usernames = self.model.password_manager.get_usernames(&self.model.current_url)?;
-        if !usernames.is_empty() {
-            let username = &usernames[0];
-            let (username, password) = self.model.password_manager.get(&self.model.current_url, username)?;
-            self.server_send(LoadUsernamePass(username.to_string(), password.to_string()));
+        self.confirm_insecure_form(false)
+    }
+
+    /// Load the credentials of `username` in the login form and submit it if `submit` is true.
+    pub fn load_username_password(&mut self, username: Option<String>, submit: bool) -> Result<()> {
+        // Do nothing when the user press Escape.
+        if let Some(username) = username {
+            let (username, password) = self.model.password_manager.get(&self.model.current_url, &username)?;
+            self.model.password_manager.set_last_username(&self.model.current_url, &username)?;
+            self.server_send(LoadUsernamePass(username, password));
+            if submit {
+                self.server_send(SubmitLoginForm());
+            }
         }
         Ok(())
     }
 
+    /// Check if the password backend can be used without blocking the interface.
+    /// Otherwise, unlock it in another thread and run `command` once it is unlocked.
+    pub fn password_backend_ready(&mut self, command: &AppCommand) -> bool {
+        if self.model.password_manager.is_unlocked() {
+            return true;
+        }
+        // Only run the last command used while unlocking.
+        self.model.password_unlock_command = Some(command.clone());
+        if self.model.password_unlock.is_none() {
+            let stream = self.model.relm.stream().clone();
+            let (channel, sender) = Channel::new(move |result| stream.emit(PasswordBackendUnlocked(result)));
+            match self.model.password_manager.unlock(sender) {
+                Ok(()) => self.model.password_unlock = Some(channel),
+                Err(error) => self.error(&error.to_string()),
+            }
+        }
+        false
+    }
+
+    /// Run the command that was waiting for the password backend to be unlocked.
+    pub fn password_backend_unlocked(&mut self, result: Result<()>) {
+        self.model.password_unlock = None;
+        let command = self.model.password_unlock_command.take();
+        match result {
+            Ok(()) =>
+                if let Some(command) = command {
+                    self.handle_command(&command);
+                },
+            Err(error) => self.error(&error.to_string()),
+        }
+    }
+
+    /// Show the result of the deletion of a password.
+    pub fn password_deleted(&mut self, result: Result<()>) {
+        self.model.password_delete = None;
+        match result {
+            Ok(()) => self.info("Password deleted".to_string()),
+            Err(error) => self.error(&error.to_string()),
+        }
+    }
+
+    /// Show the result of the saving of a password.
+    pub fn password_saved(&mut self, result: Result<bool>) {
+        self.model.password_insert = None;
+        match result {
+            Ok(true) => self.info("Password updated".to_string()),
+            Ok(false) => self.info("Password added".to_string()),
+            Err(error) => self.error(&error.to_string()),
+        }
+    }
+
     /// Fetch the login data from the web process in order to save them later.
     pub fn save_password(&mut self) {
         self.server_send(GetCredentials());
     }
 
     /// Save the password from the currently focused login form into the store.
-    pub fn save_username_password(&self, username: &str, password: &str) -> Result<()> {
-        // TODO: ask to override existing password.
-        // TODO: handle errors.
-        self.model.password_manager.add(&self.model.current_url, username, password)
-        /*Ok(true) => self.app.info("Password added"),
-          Ok(false) => self.app.info("A password is already in the store for the current URL"), // TODO: ask for a confirmation to overwrite.
-          Err(err) => self.error(err.to_string()),*/
+    pub fn save_username_password(&mut self, username: &str, password: &str) -> Result<()> {
+        if password.is_empty() {
+            return Err(Error::new("No credentials found in the login form"));
+        }
+        if username.is_empty() {
+            return Err(Error::new("No username found in the login form: enter it and use password-save"));
+        }
+        if self.model.password_insert.is_some() {
+            return Err(Error::new("A password is already being saved"));
+        }
+        let stream = self.model.relm.stream().clone();
+        let (channel, sender) = Channel::new(move |result| stream.emit(PasswordSaved(result)));
+        self.model.password_manager.add(&self.model.current_url, username, password, sender)?;
+        self.model.password_insert = Some(channel);
+        self.model.password_manager.set_last_username(&self.model.current_url, username)?;
+        Ok(())
+    }
+
+    /// Ask which username to use if there are many for the current URL, then load its
+    /// credentials in the login form.
+    pub fn select_username(&mut self, submit: bool) -> Result<()> {
+        if submit {
+            self.ask_username(PasswordUsernameSubmit)
+        }
+        else {
+            self.ask_username(PasswordUsernameLoad)
+        }
+    }
+
+    /// Use the password backend selected in the settings.
+    pub fn update_password_backend(&mut self) {
+        let backend: Box<dyn PasswordBackend> =
+            match self.model.password_backend {
+                PasswordBackendKind::Kdbx => Box::new(KdbxBackend::new(&self.model.password_kdbx_file,
+                    &self.model.password_kdbx_password_command, self.model.password_kdbx_write)),
+                PasswordBackendKind::Pass => Box::new(PassBackend),
+            };
+        self.model.password_manager.set_backend(backend);
+        // The command waiting for the previous backend is dropped.
+        // The modifications in progress are still saved, but their result is not shown.
+        self.model.password_delete = None;
+        self.model.password_insert = None;
+        self.model.password_unlock = None;
+        self.model.password_unlock_command = None;
     }
 
     /// Load the username and password in the login form and submit it.
+    /// If multiple credentials exist, ask the user which one to use.
     pub fn submit_login_form(&mut self) -> Result<()> {
-        self.load_password()?;
-        self.server_send(SubmitLoginForm());
-        Ok(())
+        self.confirm_insecure_form(true)
     }
 }
 
@@ -22,16 +22,55 @@
 use std::io;
 use std::path::PathBuf;
 
+use titanium_common::adblock::{ENGINE_CACHE_FILE, SUBSCRIPTIONS_DIR, USER_LISTS_DIR, WHITELIST_FILE};
+
 use app::App;
 use config_dir::ConfigDir;
 use errors::Result;
 
 impl App {
+    /// Get the data path of the serialized adblock engine.
+    pub fn adblock_cache_path(config_dir: &ConfigDir) -> Result<PathBuf> {
+        Ok(config_dir.data_file(ENGINE_CACHE_FILE)?)
+    }
+
+    /// Get the data path of the adblock filter list `name`.
+    pub fn adblock_list_path(config_dir: &ConfigDir, name: &str) -> Result<PathBuf> {
+        Ok(config_dir.data_file(&format!("{}/{}.txt", SUBSCRIPTIONS_DIR, name))?)
+    }
+
+    /// Get the data path of the file containing the last update time of the adblock filter list
+    /// `name`.
+    pub fn adblock_list_timestamp_path(config_dir: &ConfigDir, name: &str) -> Result<PathBuf> {
+        Ok(config_dir.data_file(&format!("{}/{}.updated", SUBSCRIPTIONS_DIR, name))?)
+    }
+
+    /// Get the directories containing the adblock filter lists written by the user and the
+    /// subscribed ones.
+    pub fn adblock_lists_dirs(config_dir: &ConfigDir) -> Result<(PathBuf, PathBuf)> {
+        Ok((config_dir.config_file(USER_LISTS_DIR)?, config_dir.data_file(SUBSCRIPTIONS_DIR)?))
+    }
+
+    /// Get the config path of the file containing the sites on which the adblocker is disabled.
+    pub fn adblock_whitelist_path(config_dir: &ConfigDir) -> io::Result<PathBuf> {
+        config_dir.config_file(WHITELIST_FILE)
+    }
+
     /// Get the config path of the bookmarks file.
     pub fn bookmark_path(config_dir: &ConfigDir) -> Result<PathBuf> {
         Ok(config_dir.config_file("bookmarks.db")?)
     }
 
+    /// Get the data path of the history file.
+    pub fn history_path(config_dir: &ConfigDir) -> Result<PathBuf> {
+        Ok(config_dir.data_file("history.db")?)
+    }
+
+    /// Get the data path of the file containing the last username used on each host.
+    pub fn last_usernames_path(config_dir: &ConfigDir) -> io::Result<PathBuf> {
+        config_dir.data_file("last-usernames")
+    }
+
     /// Get the permission whitelist and blacklist path.
     pub fn permission_path(config_dir: &ConfigDir) -> (io::Result<PathBuf>, io::Result<PathBuf>) {
         ( config_dir.config_file("permissions/whitelist"),
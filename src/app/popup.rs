@@ -22,11 +22,11 @@
 //! Popup management in the application.
 
 use mg::{Warning, question};
+use titanium_common::urls::get_base_url;
 
 use message_server::Privacy;
 use app::App;
 use app::Msg::PopupDecision;
-use urls::get_base_url;
 
 impl App {
     /// Ask to the user whether to open the popup or not (with option to whitelist or blacklist).
@@ -46,6 +46,15 @@ impl App {
         self.server_send(ActivateSelection());
     }
 
+    /// Save the position before a search in the jump list.
+    /// Only the first input of an incremental search saves the position.
+    pub fn add_search_jump(&mut self) {
+        if !self.model.search_jump_added {
+            self.model.search_jump_added = true;
+            self.server_send(AddJump());
+        }
+    }
+
     /// Click on the link to go to the next page.
     pub fn click_next_page(&mut self) {
         self.server_send(ClickNextPage());
@@ -70,7 +79,9 @@ impl App {
     /// Follow a link.
     pub fn follow_link(&mut self) {
         let chars = self.model.hint_chars.clone();
-        self.server_send(ShowHints(chars));
+        let labels = self.model.hint_labels;
+        let mode = self.model.follow_mode;
+        self.server_send(ShowHints(chars, labels, mode));
     }
 
     /// Hide the hints and return to normal mode.
@@ -79,12 +90,30 @@ impl App {
         self.go_in_normal_mode();
     }
 
+    /// Remove the last hint character in the web process.
+    pub fn remove_hint_key(&mut self) {
+        self.server_send(RemoveHintKey());
+    }
+
+    /// Go to the previous position in the jump list.
+    pub fn jump_backward(&mut self) {
+        self.server_send(JumpBackward());
+    }
+
+    /// Go to the next position in the jump list.
+    pub fn jump_forward(&mut self) {
+        self.server_send(JumpForward());
+    }
+
     pub fn message_recv(&mut self, message: InnerMessage) {
         match message {
             ActivateAction(action) => self.activate_action(action),
+            AddCosmeticStylesheet(url, css) => self.add_cosmetic_stylesheet(&url, css),
             ClickHintElement(link) => self.click_hint_element(link),
             Credentials(ref username, ref password) => handle_error!(self.save_username_password(&username, &password)),
             EnterInsertMode() => self.go_in_insert_mode(),
+            InsecureForm() => self.insecure_form_detected(),
+            RequestBlocked(url, rule, is_document) => self.request_blocked(url, rule, is_document),
             ScrollPercentage(percentage) => self.show_scroll(percentage),
             _ =>
                 // TODO: show the warning in the UI?
@@ -168,4 +197,9 @@ impl App {
         let message = UserMessage::new("", Some(&bytes.to_variant()));
         self.widgets.webview.send_message_to_page(&message, None::<&Cancellable>, |_| {});
     }
+
+    /// Switch between typing the labels of the hints and filtering the hints by their text.
+    pub fn toggle_hint_text(&mut self) {
+        self.server_send(ToggleHintText());
+    }
 }
@@ -0,0 +1,147 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Session management in the application.
+
+use glib::Bytes;
+use relm::timeout;
+use rustc_serialize::base64::{FromBase64, STANDARD, ToBase64};
+use titanium_common::Percentage::{All, Percent};
+use webkit2gtk::{
+    BackForwardListExt,
+    BackForwardListItemExt,
+    WebViewExt,
+    WebViewSessionState,
+};
+
+use app::App;
+use app::Msg::{AutosaveWindowSession, WindowSessionChanged, WindowSessionSent};
+use session::{self, HistoryItem, WindowSession};
+use webview::Msg::PageOpen;
+
+/// The delay (in milliseconds) to wait for other changes before autosaving the state of the window.
+const AUTOSAVE_DELAY: u32 = 2000;
+
+impl App {
+    /// Send the state of the window to the message server when the session autosave is enabled.
+    pub fn autosave_window_session(&self) {
+        if self.model.session_autosave {
+            if let Some(session) = self.window_session() {
+                self.model.relm.stream().emit(WindowSessionChanged(session));
+            }
+        }
+    }
+
+    /// Delete the session `name`.
+    pub fn delete_session(&self, name: &str) {
+        match session::delete(&self.model.config_dir, name) {
+            Ok(()) => self.info(format!("Deleted session {}", name)),
+            Err(error) => self.error(&error.to_string()),
+        }
+    }
+
+    /// Restore the back/forward list, the zoom level and the scroll position of the window.
+    pub fn restore_window_session(&mut self, session: WindowSession) {
+        let state = session.state.as_ref()
+            .and_then(|state| state.from_base64().ok())
+            .map(|bytes| WebViewSessionState::new(&Bytes::from_owned(bytes)));
+        let current_item =
+            if let Some(state) = state {
+                self.widgets.webview.restore_session_state(&state);
+                self.widgets.webview.back_forward_list()
+                    .and_then(|list| list.current_item())
+            }
+            else {
+                None
+            };
+        // The restored state only contains the back/forward list, so its current item needs to be
+        // loaded.
+        if let Some(item) = current_item {
+            self.widgets.webview.go_to_back_forward_list_item(&item);
+        }
+        else {
+            self.components.webview.emit(PageOpen(session.url.clone()));
+        }
+        self.widgets.webview.set_zoom_level(session.zoom_level);
+        self.model.scroll_percentage_to_restore = session.scroll_percentage;
+    }
+
+    /// Send the state of the window to the message server after a delay, to avoid saving the
+    /// session on every scroll.
+    pub fn schedule_window_session_autosave(&mut self) {
+        if self.model.session_autosave && !self.model.session_autosave_pending {
+            self.model.session_autosave_pending = true;
+            timeout(self.model.relm.stream(), AUTOSAVE_DELAY, || AutosaveWindowSession);
+        }
+    }
+
+    /// Send the state of the window to the message server, to save a session.
+    pub fn send_window_session(&self) {
+        self.model.relm.stream().emit(WindowSessionSent(self.window_session()));
+    }
+
+    /// Get the state of the window to save it in a session.
+    /// Private windows are not saved in the sessions.
+    fn window_session(&self) -> Option<WindowSession> {
+        if self.widgets.webview.is_ephemeral() {
+            None
+        }
+        else {
+            self.window_state()
+        }
+    }
+
+    /// Get the state of the window.
+    pub fn window_state(&self) -> Option<WindowSession> {
+        let webview = &self.widgets.webview;
+        let url = webview.uri()?;
+        let mut history = vec![];
+        if let Some(list) = webview.back_forward_list() {
+            let items = list.back_list().into_iter()
+                .chain(list.current_item())
+                .chain(list.forward_list());
+            for item in items {
+                if let Some(url) = item.uri() {
+                    history.push(HistoryItem {
+                        title: item.title().map(Into::into).unwrap_or_default(),
+                        url: url.into(),
+                    });
+                }
+            }
+        }
+        let state = webview.session_state()
+            .and_then(|state| state.serialize())
+            .map(|bytes| bytes.to_base64(STANDARD));
+        let scroll_percentage =
+            match self.model.scroll_percentage {
+                All => None,
+                Percent(percent) => Some(percent),
+            };
+        Some(WindowSession {
+            history,
+            scroll_percentage,
+            state,
+            title: webview.title().map(Into::into).unwrap_or_default(),
+            url: url.into(),
+            zoom_level: webview.zoom_level(),
+        })
+    }
+}
@@ -24,7 +24,7 @@ use webkit2gtk::WebViewExt;
 use titanium_common::FollowMode;
 
 use app::App;
-use app::Msg::CreateWindow;
+use app::Msg::{CreateBackgroundWindow, CreateWindow};
 use message_server::Privacy;
 use webview::Msg::PageOpen;
 use url::{Url, Position};
@@ -37,6 +37,19 @@ impl App {
         self.components.webview.emit(PageOpen(url));
     }
 
+    /// Open the given URL in a new window while keeping the focus on this window.
+    pub fn open_in_background_window(&self, url: &str) {
+        let privacy =
+            if self.widgets.webview.is_ephemeral() {
+                Privacy::Private
+            }
+            else {
+                Privacy::Normal
+            };
+        let url = self.transform_url(url);
+        self.model.relm.stream().emit(CreateBackgroundWindow(url, privacy));
+    }
+
     /// Open the given URL in a new window.
     pub fn open_in_new_window(&self, url: &str, privacy: Privacy) {
         let privacy =
@@ -0,0 +1,283 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Import and export of the bookmarks in the Netscape HTML and JSON formats.
+
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+use rustc_serialize::json;
+
+use bookmarks::BookmarkRecord;
+use errors::Result;
+use html::{decode_entities, encode_entities};
+
+/// A bookmark in the JSON format.
+/// The optional fields allow importing files written by hand.
+#[derive(RustcDecodable, RustcEncodable)]
+struct JsonBookmark {
+    tags: Option<Vec<String>>,
+    title: Option<String>,
+    url: String,
+    visit_count: Option<i64>,
+}
+
+/// Export the `bookmarks` to the file at `path`.
+/// The JSON format is used when the file has the json extension, otherwise the Netscape HTML
+/// format is used.
+pub fn export(path: &Path, bookmarks: &[BookmarkRecord]) -> Result<()> {
+    write(path, serialize(path, bookmarks))?;
+    Ok(())
+}
+
+/// Import the bookmarks from the file at `path`.
+/// The JSON format is used when the file has the json extension, otherwise the Netscape HTML
+/// format is used.
+pub fn import(path: &Path) -> Result<Vec<BookmarkRecord>> {
+    let content = read_to_string(path)?;
+    parse(path, &content)
+}
+
+/// Get the value of the attribute `name` in the HTML `tag`.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!(" {}=\"", name);
+    let start = tag.to_ascii_lowercase().find(&pattern)? + pattern.len();
+    let end = tag[start..].find('"')?;
+    Some(decode_entities(&tag[start..start + end]))
+}
+
+/// Parse the bookmarks from a Netscape HTML file.
+/// The folders are converted to tags, except the toolbar and unfiled folders of the browsers.
+fn from_html(html: &str) -> Vec<BookmarkRecord> {
+    // The lowercase version is only used to find the closing tags, so only convert the ASCII
+    // characters to keep the same indices.
+    let lowercase_html = html.to_ascii_lowercase();
+    let mut bookmarks = vec![];
+    // The tags of the folders (<DL>) containing the current position.
+    let mut folders: Vec<Option<String>> = vec![];
+    let mut next_folder = None;
+    let mut index = 0;
+    while let Some(start) = html[index..].find('<') {
+        let start = index + start + 1;
+        let end =
+            match html[start..].find('>') {
+                Some(end) => start + end,
+                None => break,
+            };
+        let tag = &html[start..end];
+        index = end + 1;
+        let name = tag.split_whitespace().next().unwrap_or("").to_ascii_lowercase();
+        match name.as_str() {
+            "a" => {
+                let text_end = lowercase_html[index..].find("</a>").map(|end| index + end).unwrap_or(index);
+                let title = decode_entities(html[index..text_end].trim());
+                index = text_end;
+                if let Some(url) = attribute(tag, "href") {
+                    let mut tags: Vec<String> = folders.iter()
+                        .filter_map(Clone::clone)
+                        .collect();
+                    if let Some(tag_list) = attribute(tag, "tags") {
+                        for tag in tag_list.split(',') {
+                            let tag = normalize_tag(tag);
+                            if !tag.is_empty() && !tags.contains(&tag) {
+                                tags.push(tag);
+                            }
+                        }
+                    }
+                    let visit_count = attribute(tag, "visit_count")
+                        .and_then(|count| count.parse().ok())
+                        .unwrap_or(0);
+                    bookmarks.push(BookmarkRecord {
+                        tags,
+                        title,
+                        url,
+                        visit_count,
+                    });
+                }
+            },
+            "dl" => folders.push(next_folder.take().unwrap_or(None)),
+            "/dl" => {
+                folders.pop();
+            },
+            "h3" => {
+                let text_end = lowercase_html[index..].find("</h3>").map(|end| index + end).unwrap_or(index);
+                let folder = normalize_tag(&decode_entities(&html[index..text_end]));
+                index = text_end;
+                let lowercase_tag = tag.to_ascii_lowercase();
+                let special_folder = lowercase_tag.contains("personal_toolbar_folder=\"true\"") ||
+                    lowercase_tag.contains("unfiled_bookmarks_folder=\"true\"");
+                if special_folder || folder.is_empty() {
+                    next_folder = Some(None);
+                }
+                else {
+                    next_folder = Some(Some(folder));
+                }
+            },
+            _ => (),
+        }
+    }
+    bookmarks
+}
+
+fn from_json(content: &str) -> Result<Vec<BookmarkRecord>> {
+    let bookmarks: Vec<JsonBookmark> = json::decode(content)?;
+    Ok(bookmarks.into_iter()
+        .map(|bookmark| BookmarkRecord {
+            tags: bookmark.tags.unwrap_or_default().iter()
+                .map(|tag| normalize_tag(tag))
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+            title: bookmark.title.unwrap_or_default(),
+            url: bookmark.url,
+            visit_count: bookmark.visit_count.unwrap_or(0),
+        })
+        .collect())
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+}
+
+/// Convert a folder name or a tag from another browser to a tag usable in the completions (which
+/// cannot contain spaces).
+fn normalize_tag(tag: &str) -> String {
+    tag.trim()
+        .to_lowercase()
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Parse the bookmarks from the `content` of the file at `path`, in the format chosen from its
+/// extension.
+fn parse(path: &Path, content: &str) -> Result<Vec<BookmarkRecord>> {
+    if is_json(path) {
+        from_json(content)
+    }
+    else {
+        Ok(from_html(content))
+    }
+}
+
+/// Convert the `bookmarks` to the format chosen from the extension of the file at `path`.
+fn serialize(path: &Path, bookmarks: &[BookmarkRecord]) -> String {
+    if is_json(path) {
+        to_json(bookmarks)
+    }
+    else {
+        to_html(bookmarks)
+    }
+}
+
+fn to_html(bookmarks: &[BookmarkRecord]) -> String {
+    let mut html = "<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+".to_string();
+    for bookmark in bookmarks {
+        let tags =
+            if !bookmark.tags.is_empty() {
+                format!(" TAGS=\"{}\"", encode_entities(&bookmark.tags.join(",")))
+            }
+            else {
+                String::new()
+            };
+        html.push_str(&format!("    <DT><A HREF=\"{}\"{} VISIT_COUNT=\"{}\">{}</A>\n", encode_entities(&bookmark.url),
+            tags, bookmark.visit_count, encode_entities(&bookmark.title)));
+    }
+    html.push_str("</DL><p>\n");
+    html
+}
+
+fn to_json(bookmarks: &[BookmarkRecord]) -> String {
+    let bookmarks: Vec<_> = bookmarks.iter()
+        .map(|bookmark| JsonBookmark {
+            tags: Some(bookmark.tags.clone()),
+            title: Some(bookmark.title.clone()),
+            url: bookmark.url.clone(),
+            visit_count: Some(bookmark.visit_count),
+        })
+        .collect();
+    json::as_pretty_json(&bookmarks).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use bookmarks::BookmarkRecord;
+    use super::{from_html, parse, serialize};
+
+    #[test]
+    fn test_from_html() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 PERSONAL_TOOLBAR_FOLDER="true">Bookmarks bar</H3>
+    <DL><p>
+        <DT><A HREF="https://www.rust-lang.org/" TAGS="lang">Rust</A>
+        <DT><H3>Web Browsers</H3>
+        <DL><p>
+            <DT><A HREF="https://github.com/antoyo/titanium?a=1&amp;b=2" VISIT_COUNT="3">Titanium &amp; co</A>
+        </DL><p>
+    </DL><p>
+    <DT><A HREF="https://example.com/">Example</A>
+</DL><p>
+"#;
+        let bookmarks = from_html(html);
+        assert_eq!(bookmarks.len(), 3);
+        assert_eq!(bookmarks[0].url, "https://www.rust-lang.org/");
+        assert_eq!(bookmarks[0].tags, vec!["lang".to_string()]);
+        assert_eq!(bookmarks[1].url, "https://github.com/antoyo/titanium?a=1&b=2");
+        assert_eq!(bookmarks[1].title, "Titanium & co");
+        assert_eq!(bookmarks[1].tags, vec!["web-browsers".to_string()]);
+        assert_eq!(bookmarks[1].visit_count, 3);
+        assert!(bookmarks[2].tags.is_empty());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let bookmarks = vec![
+            BookmarkRecord {
+                tags: vec!["lang".to_string(), "rust".to_string()],
+                title: "Rust \"lang\"".to_string(),
+                url: "https://www.rust-lang.org/".to_string(),
+                visit_count: 3,
+            },
+            BookmarkRecord {
+                tags: vec![],
+                title: String::new(),
+                url: "https://example.com/?a=1&b=2".to_string(),
+                visit_count: 0,
+            },
+        ];
+        let path = Path::new("bookmarks.JSON");
+        let json = serialize(path, &bookmarks);
+        assert!(json.trim_start().starts_with('['));
+        let imported = parse(path, &json).expect("valid JSON");
+        assert_eq!(imported, bookmarks);
+    }
+}
@@ -26,7 +26,7 @@ use std::collections::HashSet;
 use std::path::PathBuf;
 use std::result;
 
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
 use rusqlite::types::ToSql;
 
 use errors::{Error, Result};
@@ -54,6 +54,22 @@ impl Bookmark {
     }
 }
 
+/// A bookmark with all its data, as imported from or exported to a file.
+#[derive(Debug, PartialEq)]
+pub struct BookmarkRecord {
+    pub tags: Vec<String>,
+    pub title: String,
+    pub url: String,
+    pub visit_count: i64,
+}
+
+/// A quickmark is a global mark saving a URL and, optionally, the scroll position in the page.
+#[derive(Debug)]
+pub struct Quickmark {
+    pub scroll_percentage: Option<i64>,
+    pub url: String,
+}
+
 /// A bookmark manager is use to add, search and remove bookmarks.
 pub struct BookmarkManager {
 }
@@ -82,6 +98,53 @@ impl BookmarkManager {
         })
     }
 
+    /// Add a tag to a bookmark, creating the tag if needed.
+    fn add_tag(&self, connection: &Connection, bookmark_id: i32, tag: &str) -> Result<()> {
+        connection.execute("
+            INSERT OR IGNORE INTO tags (name)
+            VALUES ($1)
+        ", &[&tag])?;
+        let tag_id = self.get_tag_id(connection, tag)?;
+        connection.execute("
+            INSERT OR IGNORE INTO bookmarks_tags (bookmark_id, tag_id)
+            VALUES ($1, $2)
+        ", &[&bookmark_id, &tag_id])?;
+        Ok(())
+    }
+
+    /// Get all the bookmarks with their tags and visit count.
+    pub fn all(&self) -> Result<Vec<BookmarkRecord>> {
+        CONNECTION.with(|connection| {
+            if let Some(ref connection) = *connection.borrow() {
+                let mut statement = connection.prepare("
+                    SELECT title, url, visit_count, COALESCE(GROUP_CONCAT(tags.name, ','), '')
+                    FROM bookmarks
+                    LEFT OUTER JOIN bookmarks_tags
+                        ON bookmarks.id = bookmarks_tags.bookmark_id
+                    LEFT OUTER JOIN tags
+                        ON bookmarks_tags.tag_id = tags.id
+                    GROUP BY bookmarks.id
+                    ORDER BY bookmarks.id
+                ")?;
+                let rows = statement.query_map([], |row| {
+                    let tags: String = row.get(3)?;
+                    Ok(BookmarkRecord {
+                        tags: tags.split(',')
+                            .filter(|tag| !tag.is_empty())
+                            .map(ToString::to_string)
+                            .collect(),
+                        title: row.get(0)?,
+                        url: row.get(1)?,
+                        visit_count: row.get(2)?,
+                    })
+                })?;
+                return rows.collect::<result::Result<Vec<_>, _>>()
+                    .map_err(Into::into);
+            }
+            Ok(vec![])
+        })
+    }
+
     /// Connect to the database if it is not already connected.
     pub fn connect(&self, filename: PathBuf) -> Result<()> {
         CONNECTION.with(|connection| {
@@ -122,6 +185,13 @@ impl BookmarkManager {
                 , FOREIGN KEY(bookmark_id) REFERENCES bookmarks(id) ON DELETE CASCADE
                 , FOREIGN KEY(tag_id) REFERENCES tags(id) ON DELETE CASCADE
                 )", [])?;
+
+                connection.execute("
+                CREATE TABLE IF NOT EXISTS quickmarks
+                ( mark TEXT PRIMARY KEY
+                , url TEXT NOT NULL
+                , scroll_percentage INTEGER
+                )", [])?;
             }
             Ok(())
         })
@@ -226,6 +296,39 @@ impl BookmarkManager {
         })
     }
 
+    /// Import the bookmarks.
+    /// The bookmarks that already exist keep their title, but get the new tags.
+    /// Returns the number of bookmarks that were added.
+    pub fn import(&self, bookmarks: &[BookmarkRecord]) -> Result<usize> {
+        CONNECTION.with(|connection| {
+            let mut added_count = 0;
+            if let Some(ref connection) = *connection.borrow() {
+                let transaction = connection.unchecked_transaction()?;
+                for bookmark in bookmarks {
+                    added_count += transaction.execute("
+                        INSERT OR IGNORE INTO bookmarks (title, url, visit_count)
+                        VALUES ($1, $2, $3)
+                        ", &[&bookmark.title as &dyn ToSql, &bookmark.url, &bookmark.visit_count])?;
+                    transaction.execute("
+                        UPDATE bookmarks
+                        SET visit_count = MAX(visit_count, $1)
+                        WHERE url = $2
+                        ", &[&bookmark.visit_count as &dyn ToSql, &bookmark.url])?;
+                    let bookmark_id = transaction.query_row("
+                        SELECT id
+                        FROM bookmarks
+                        WHERE url = $1
+                        ", &[&bookmark.url], |row| row.get(0))?;
+                    for tag in &bookmark.tags {
+                        self.add_tag(&transaction, bookmark_id, &tag.to_lowercase())?;
+                    }
+                }
+                transaction.commit()?;
+            }
+            Ok(added_count)
+        })
+    }
+
     /// Query the bookmarks.
     pub fn query(&self, input: BookmarkInput) -> Vec<Bookmark> {
         CONNECTION.with(|connection| {
@@ -292,6 +395,27 @@ impl BookmarkManager {
         })
     }
 
+    /// Get the quickmark saved for `mark`.
+    pub fn quickmark(&self, mark: char) -> Result<Option<Quickmark>> {
+        CONNECTION.with(|connection| {
+            if let Some(ref connection) = *connection.borrow() {
+                return connection.query_row("
+                    SELECT url, scroll_percentage
+                    FROM quickmarks
+                    WHERE mark = $1
+                    ", &[&mark.to_string()], |row| {
+                        Ok(Quickmark {
+                            scroll_percentage: row.get(1)?,
+                            url: row.get(0)?,
+                        })
+                    })
+                    .optional()
+                    .map_err(Into::into);
+            }
+            Ok(None)
+        })
+    }
+
     pub fn search_tags(&self, tag_name: &str) -> Result<Vec<String>> {
         CONNECTION.with(|connection| {
             if let Some(ref connection) = *connection.borrow() {
@@ -314,6 +438,19 @@ impl BookmarkManager {
         })
     }
 
+    /// Save the `url` and the scroll position for the quickmark `mark`, replacing the previous one.
+    pub fn set_quickmark(&self, mark: char, url: &str, scroll_percentage: Option<i64>) -> Result<()> {
+        CONNECTION.with(|connection| {
+            if let Some(ref connection) = *connection.borrow() {
+                connection.execute("
+                    INSERT OR REPLACE INTO quickmarks (mark, url, scroll_percentage)
+                    VALUES ($1, $2, $3)
+                    ", &[&mark.to_string() as &dyn ToSql, &url, &scroll_percentage])?;
+            }
+            Ok(())
+        })
+    }
+
     /// Set the tags of a bookmark.
     pub fn set_tags(&self, url: &str, tags: Vec<String>) -> Result<()> {
         let original_tags = self.get_tags(url)?;
@@ -321,17 +458,7 @@ impl BookmarkManager {
             if let Some(bookmark_id) = self.get_id(url) {
                 if let Some(ref connection) = *connection.borrow() {
                     for tag in &tags {
-                        let tag = tag.to_lowercase();
-                        connection.execute("
-                            INSERT OR IGNORE INTO tags (name)
-                            VALUES ($1)
-                        ", &[&tag])?;
-                        let tag_id = self.get_tag_id(connection, &tag)?;
-                        connection.execute("
-                            INSERT OR IGNORE INTO bookmarks_tags (bookmark_id, tag_id)
-                            VALUES ($1, $2)
-                        ", &[&bookmark_id, &tag_id])
-                            .map(|_| ())?
+                        self.add_tag(connection, bookmark_id, &tag.to_lowercase())?;
                     }
                     self.delete_tags(connection, bookmark_id, &original_tags, &tags)?;
                 }
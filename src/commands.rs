@@ -23,7 +23,15 @@
 pub enum AppCommand {
     #[completion(hidden)]
     ActivateSelection,
-    #[help(text="Update the host file used by the adblocker")]
+    #[help(text="Disable the adblocker on the current site")]
+    AdblockDisableSite,
+    #[help(text="Enable the adblocker on the current site")]
+    AdblockEnableSite,
+    #[completion(hidden)]
+    AdblockList(String),
+    #[help(text="Show the requests blocked in this window (select one to copy its URL)")]
+    AdblockLog(String),
+    #[help(text="Update the filter lists used by the adblocker")]
     AdblockUpdate,
     #[help(text="Add a new user agent")]
     AddUserAgent(String),
@@ -37,6 +45,10 @@ pub enum AppCommand {
     BookmarkDel,
     #[help(text="Edit the bookmark tags of the current page")]
     BookmarkEditTags,
+    #[help(text="Export the bookmarks to an HTML or JSON file")]
+    BookmarkExport(String),
+    #[help(text="Import the bookmarks from an HTML or JSON file")]
+    BookmarkImport(String),
     #[help(text="Clear the browser cache")]
     ClearCache,
     #[help(text="Try to click link to next page if it exists")]
@@ -44,8 +56,12 @@ pub enum AppCommand {
     #[help(text="Try to click link to the previous page if it exists")]
     ClickPrevPage,
     #[completion(hidden)]
+    CopyAnchorUrl,
+    #[completion(hidden)]
     CopyLinkUrl,
     #[completion(hidden)]
+    CopyText,
+    #[completion(hidden)]
     CopyUrl,
     #[help(text="Delete all the cookies")]
     DeleteAllCookies,
@@ -56,12 +72,16 @@ pub enum AppCommand {
     #[completion(hidden)]
     FinishSearch,
     #[completion(hidden)]
+    FocusElement,
+    #[completion(hidden)]
     FocusInput,
     #[completion(hidden)]
     Follow,
     #[help(text="Go forward in the history")]
     Forward,
     #[completion(hidden)]
+    GoAnchor,
+    #[completion(hidden)]
     GoMark(String),
     #[count]
     #[help(text="Go up one directory in url")]
@@ -71,11 +91,23 @@ pub enum AppCommand {
     #[completion(hidden)]
     HideHints,
     #[completion(hidden)]
+    HintText,
+    #[help(text="Open an URL from the history")]
+    History(String),
+    #[help(text="Clear the history for a time range (last hour, today or all)")]
+    HistoryClear(String),
+    #[completion(hidden)]
+    HistoryDelete,
+    #[completion(hidden)]
     Hover,
     #[completion(hidden)]
     Insert,
     #[help(text="Open the web inspector")]
     Inspector,
+    #[help(text="Go to the previous position in the jump list")]
+    JumpBackward,
+    #[help(text="Go to the next position in the jump list")]
+    JumpForward,
     #[help(text="Kill the webview without confirmation")]
     KillWin,
     #[completion(hidden)]
@@ -86,8 +118,12 @@ pub enum AppCommand {
     Open(String),
     #[help(text="Delete the credentials for the current URL")]
     PasswordDelete,
+    #[help(text="Generate a password in the form and save it (arguments: [length] [all|alnum|alpha|digits|hex|chars:characters])")]
+    PasswordGenerate(Option<String>),
     #[help(text="Insert a password in the focused text input")]
     PasswordInsert,
+    #[help(text="Insert the one-time password in the focused text input")]
+    PasswordInsertOtp,
     #[help(text="Insert a password in the focused text input and submit the form")]
     PasswordInsertSubmit,
     #[help(text="Load the credentials in the login form")]
@@ -102,10 +138,14 @@ pub enum AppCommand {
     PreferredLanguage(String),
     #[help(text="Print the current page")]
     Print,
+    #[completion(hidden)]
+    PrivateWinFollow,
     #[help(text="Open an URL in a new private window")]
     PrivateWinOpen(String),
     #[help(text="Quit the application")]
     Quit,
+    #[completion(hidden)]
+    RapidFollow,
     #[help(text="Reload the current page")]
     Reload,
     #[help(text="Reload the current page without using the cache")]
@@ -113,6 +153,8 @@ pub enum AppCommand {
     #[help(text="Restore the opened pages after a crash")]
     RestoreUrls,
     #[completion(hidden)]
+    SaveImage,
+    #[completion(hidden)]
     SaveLink,
     #[completion(hidden)]
     SearchEngine(String),
@@ -132,6 +174,8 @@ pub enum AppCommand {
     #[completion(hidden)]
     ScrollRight,
     #[completion(hidden)]
+    ScrollToElement,
+    #[completion(hidden)]
     ScrollTop,
     #[completion(hidden)]
     ScrollUp,
@@ -145,10 +189,22 @@ pub enum AppCommand {
     SearchNext,
     #[completion(hidden)]
     SearchPrevious,
+    #[completion(hidden)]
+    SelectText,
     #[help(text="Select a user agent by name")]
     SelectUserAgent(String),
+    #[help(text="Delete a saved session")]
+    SessionDelete(String),
+    #[help(text="Open the windows of a saved session")]
+    SessionLoad(String),
+    #[help(text="Save the opened windows in a session")]
+    SessionSave(String),
     #[help(text="Stop loading the current page")]
     Stop,
+    #[help(text="Reopen the last closed window")]
+    UndoClose,
+    #[help(text="Reopen a closed window selected in the completions")]
+    UndoCloseSelect(String),
     #[completion(hidden)]
     UrlIncrement,
     #[completion(hidden)]
@@ -19,21 +19,76 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
+use std::cell::RefCell;
 use std::cmp::Ordering::{Greater, Less};
+use std::collections::HashMap;
 use std::fs::read_dir;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 use mg::completion::{Completer, CompletionCell, CompletionResult};
 use mg::completion::Column::{self, AllVisible, Expand};
 
+use adblock_manager::BlockedRequests;
 use app::USER_AGENT_COMPLETER;
 use app::user_agent::UserAgentManager;
 use bookmarks::{BookmarkInput, BookmarkManager};
+use config_dir::ConfigDir;
 use download::download_dir;
+use history::HistoryManager;
+use message_server::{ClosedWindows, Privacy};
+use session;
 
-/// A bookmark completer.
+/// Frecency added to the bookmarks so that they come before the history entries visited as often.
+const BOOKMARK_FRECENCY_BONUS: i64 = 100;
+
+/// A completer for the requests blocked by the adblocker in a window.
+pub struct AdblockLogCompleter {
+    blocked_requests: BlockedRequests,
+}
+
+impl AdblockLogCompleter {
+    /// Create a new adblock log completer.
+    pub fn new(blocked_requests: BlockedRequests) -> Self {
+        AdblockLogCompleter {
+            blocked_requests,
+        }
+    }
+}
+
+impl Completer for AdblockLogCompleter {
+    fn columns(&self) -> Vec<Column> {
+        vec![Expand, Expand]
+    }
+
+    fn complete_result(&self, value: &str) -> String {
+        format!("adblock-log {}", value)
+    }
+
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        let words: Vec<_> = input.to_lowercase()
+            .split_whitespace()
+            .map(ToString::to_string)
+            .collect();
+        self.blocked_requests.borrow().iter()
+            .filter(|request| {
+                let rule = request.rule.to_lowercase();
+                let url = request.url.to_lowercase();
+                words.iter().all(|word| rule.contains(word) || url.contains(word))
+            })
+            .map(|request| CompletionResult::new(&[&request.rule, &request.url]))
+            .collect()
+    }
+
+    fn text_column(&self) -> i32 {
+        1
+    }
+}
+
+/// A completer for the bookmarks and the history.
 pub struct BookmarkCompleter {
     bookmarks: BookmarkManager,
+    history: HistoryManager,
     prefix: &'static str,
 }
 
@@ -42,6 +97,7 @@ impl BookmarkCompleter {
     pub fn new(prefix: &'static str) -> Self {
         BookmarkCompleter {
             bookmarks: BookmarkManager::new(),
+            history: HistoryManager::new(),
             prefix: prefix,
         }
     }
@@ -82,10 +138,22 @@ impl Completer for BookmarkCompleter {
     }
 
     fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
-        let mut results = vec![];
         let query = BookmarkCompleter::parse_input(input);
+        // The history has no tags, so only search the bookmarks when tags are specified.
+        let history =
+            if query.tags.is_empty() {
+                self.history.query(&query.words)
+            }
+            else {
+                vec![]
+            };
+        let mut frecencies: HashMap<_, _> = history.iter()
+            .map(|entry| (entry.url.clone(), entry.frecency))
+            .collect();
 
+        let mut entries = vec![];
         for bookmark in self.bookmarks.query(query) {
+            let frecency = frecencies.remove(&bookmark.url).unwrap_or(0) + BOOKMARK_FRECENCY_BONUS;
             let tags =
                 if !bookmark.tags.is_empty() {
                     format!("#{}", bookmark.tags)
@@ -93,12 +161,112 @@ impl Completer for BookmarkCompleter {
                 else {
                     String::new()
                 };
-            results.push(CompletionResult::from_cells(
-                &[&bookmark.title, &CompletionCell::new(&tags).foreground("#33DD00"), &bookmark.url],
-            ));
+            entries.push((frecency, bookmark.title, tags, bookmark.url));
         }
+        for entry in history {
+            // The bookmarked entries were removed from this map.
+            if let Some(frecency) = frecencies.remove(&entry.url) {
+                entries.push((frecency, entry.title, String::new(), entry.url));
+            }
+        }
+        entries.sort_by(|entry1, entry2| entry2.0.cmp(&entry1.0));
 
-        results
+        entries.iter()
+            .map(|&(_, ref title, ref tags, ref url)| CompletionResult::from_cells(
+                &[title, &CompletionCell::new(tags).foreground("#33DD00"), url],
+            ))
+            .collect()
+    }
+
+    fn text_column(&self) -> i32 {
+        2
+    }
+}
+
+/// A completer for the history only.
+pub struct HistoryCompleter {
+    history: HistoryManager,
+}
+
+impl HistoryCompleter {
+    /// Create a new history completer.
+    pub fn new() -> Self {
+        HistoryCompleter {
+            history: HistoryManager::new(),
+        }
+    }
+}
+
+impl Completer for HistoryCompleter {
+    fn columns(&self) -> Vec<Column> {
+        vec![Expand, Expand]
+    }
+
+    fn complete_result(&self, value: &str) -> String {
+        format!("history {}", value)
+    }
+
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        let words: Vec<_> = input.to_lowercase()
+            .split_whitespace()
+            .map(ToString::to_string)
+            .collect();
+        self.history.query(&words).iter()
+            .map(|entry| CompletionResult::new(&[&entry.title, &entry.url]))
+            .collect()
+    }
+
+    fn text_column(&self) -> i32 {
+        1
+    }
+}
+
+/// A completer for the closed windows that can be reopened.
+pub struct ClosedWindowCompleter {
+    closed_windows: ClosedWindows,
+}
+
+impl ClosedWindowCompleter {
+    /// Create a new closed window completer.
+    pub fn new(closed_windows: ClosedWindows) -> Self {
+        ClosedWindowCompleter {
+            closed_windows,
+        }
+    }
+}
+
+impl Completer for ClosedWindowCompleter {
+    fn columns(&self) -> Vec<Column> {
+        vec![AllVisible, Expand, Expand]
+    }
+
+    fn complete_result(&self, value: &str) -> String {
+        format!("undo-close-select {}", value)
+    }
+
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        let words: Vec<_> = input.to_lowercase()
+            .split_whitespace()
+            .map(ToString::to_string)
+            .collect();
+        self.closed_windows.borrow().iter()
+            .filter(|window| {
+                let title = window.session.title.to_lowercase();
+                let url = window.session.url.to_lowercase();
+                words.iter().all(|word| title.contains(word) || url.contains(word))
+            })
+            .map(|window| {
+                let privacy =
+                    if window.privacy == Privacy::Private {
+                        "[PV]"
+                    }
+                    else {
+                        ""
+                    };
+                CompletionResult::from_cells(&[&CompletionCell::new(privacy).foreground("#FF33FF"),
+                    &window.session.title, &window.session.url])
+            })
+            .collect()
     }
 
     fn text_column(&self) -> i32 {
@@ -240,6 +408,39 @@ impl Completer for TagCompleter {
     }
 }
 
+/// A completer for the names of the saved sessions.
+pub struct SessionCompleter {
+    config_dir: ConfigDir,
+    prefix: &'static str,
+}
+
+impl SessionCompleter {
+    /// Create a new session completer.
+    pub fn new(prefix: &'static str, config_dir: ConfigDir) -> Self {
+        SessionCompleter {
+            config_dir,
+            prefix,
+        }
+    }
+}
+
+impl Completer for SessionCompleter {
+    fn columns(&self) -> Vec<Column> {
+        vec![Expand]
+    }
+
+    fn complete_result(&self, value: &str) -> String {
+        format!("{} {}", self.prefix, value)
+    }
+
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        session::list(&self.config_dir).iter()
+            .filter(|name| name.contains(input.trim()))
+            .map(|name| CompletionResult::new(&[&name]))
+            .collect()
+    }
+}
+
 /// A user agent completer.
 pub struct UserAgentCompleter {
     manager: UserAgentManager,
@@ -274,6 +475,37 @@ impl Completer for UserAgentCompleter {
     }
 }
 
+/// A completer for the usernames having credentials on the current page.
+pub struct UsernameCompleter {
+    usernames: Rc<RefCell<Vec<String>>>,
+}
+
+impl UsernameCompleter {
+    pub fn new(usernames: Rc<RefCell<Vec<String>>>) -> Self {
+        Self {
+            usernames,
+        }
+    }
+}
+
+impl Completer for UsernameCompleter {
+    fn columns(&self) -> Vec<Column> {
+        vec![Expand]
+    }
+
+    fn complete_result(&self, value: &str) -> String {
+        value.to_string()
+    }
+
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        let input = input.to_lowercase();
+        self.usernames.borrow().iter()
+            .filter(|username| username.to_lowercase().contains(&input))
+            .map(|username| CompletionResult::new(&[username]))
+            .collect()
+    }
+}
+
 /// Split at whitespaces and at the # character.
 /// The # character will be kept in the words while the spaces are dropped.
 fn split_whitespace_and_hash(input: &str) -> Vec<String> {
@@ -27,7 +27,7 @@ use std::str::Utf8Error;
 use glib;
 use password_store;
 use rusqlite;
-use zip::result::ZipError;
+use rustc_serialize::json::DecoderError;
 
 pub struct Error {
     msg: String,
@@ -61,6 +61,14 @@ impl<'a> From<&'a str> for Error {
     }
 }
 
+impl From<DecoderError> for Error {
+    fn from(error: DecoderError) -> Self {
+        Error {
+            msg: error.to_string(),
+        }
+    }
+}
+
 impl From<glib::Error> for Error {
     fn from(error: glib::Error) -> Self {
         Error {
@@ -101,12 +109,4 @@ impl From<Utf8Error> for Error {
     }
 }
 
-impl From<ZipError> for Error {
-    fn from(error: ZipError) -> Self {
-        Error {
-            msg: error.to_string(),
-        }
-    }
-}
-
 pub type Result<T> = result::Result<T, Error>;
@@ -0,0 +1,310 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Browsing history management.
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::result;
+
+use glib::DateTime;
+use rusqlite::Connection;
+use rusqlite::types::ToSql;
+
+use errors::{Error, Result};
+
+thread_local! {
+    static CONNECTION: RefCell<Option<Connection>> = RefCell::new(None);
+}
+
+/// Maximum number of history entries returned by a query.
+const QUERY_LIMIT: u32 = 100;
+
+/// An history entry has a title, a URL and a frecency score (a mix of the visit count and the
+/// time since the last visit).
+#[derive(Debug)]
+pub struct HistoryEntry {
+    pub frecency: i64,
+    pub title: String,
+    pub url: String,
+}
+
+/// A time range of the history to clear.
+#[derive(Clone, Copy)]
+pub enum HistoryRange {
+    All,
+    LastHour,
+    Today,
+}
+
+impl HistoryRange {
+    /// Parse a range like "last hour", "today" or "all".
+    pub fn parse(range: &str) -> Result<Self> {
+        let words: Vec<_> = range.split(|c: char| c.is_whitespace() || c == '-')
+            .filter(|word| !word.is_empty())
+            .collect();
+        match words.as_slice() {
+            ["all"] => Ok(HistoryRange::All),
+            ["last", "hour"] | ["hour"] => Ok(HistoryRange::LastHour),
+            ["today"] => Ok(HistoryRange::Today),
+            _ => Err(Error::from_string(format!("Unknown history range: {} (expected last hour, today or all)", range))),
+        }
+    }
+
+    /// Get the number of seconds covered by this range, or `None` for the whole history.
+    pub fn seconds(&self) -> Option<i64> {
+        match *self {
+            HistoryRange::All => None,
+            HistoryRange::LastHour => Some(3600),
+            HistoryRange::Today => {
+                // Seconds since midnight (local time).
+                let now = DateTime::now_local().ok()?;
+                Some(now.hour() as i64 * 3600 + now.minute() as i64 * 60 + now.second() as i64)
+            },
+        }
+    }
+}
+
+/// An history manager is used to record the visited pages and to search them.
+pub struct HistoryManager {
+}
+
+impl HistoryManager {
+    /// Create a new history manager.
+    pub fn new() -> Self {
+        HistoryManager {
+        }
+    }
+
+    /// Record a visit of the page at `url`.
+    /// This adds the page to the history or increments its visit count.
+    pub fn add_visit(&self, url: &str, title: Option<String>) -> Result<()> {
+        CONNECTION.with(|connection| {
+            if let Some(ref connection) = *connection.borrow() {
+                let title = title.unwrap_or_default();
+                connection.execute("
+                    INSERT INTO history (title, url, visit_count, last_visit)
+                    VALUES ($1, $2, 1, CAST(strftime('%s', 'now') AS INTEGER))
+                    ON CONFLICT(url) DO UPDATE SET
+                        title = CASE WHEN $1 = '' THEN title ELSE $1 END,
+                        visit_count = visit_count + 1,
+                        last_visit = CAST(strftime('%s', 'now') AS INTEGER)
+                    ", &[&title, &url.to_string()])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Delete the history entries visited in the specified `range`.
+    pub fn clear(&self, range: HistoryRange) -> Result<()> {
+        CONNECTION.with(|connection| {
+            if let Some(ref connection) = *connection.borrow() {
+                match range.seconds() {
+                    Some(seconds) => {
+                        connection.execute("
+                            DELETE FROM history
+                            WHERE last_visit >= CAST(strftime('%s', 'now') AS INTEGER) - $1
+                            ", &[&seconds])?;
+                    },
+                    None => {
+                        connection.execute("DELETE FROM history", [])?;
+                    },
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Connect to the database if it is not already connected.
+    pub fn connect(&self, filename: PathBuf) -> Result<()> {
+        CONNECTION.with(|connection| {
+            let mut connection = connection.borrow_mut();
+            if connection.is_none() {
+                *connection = Some(Connection::open(filename)?);
+            }
+            Ok(())
+        })
+    }
+
+    /// Create the SQL tables for the history.
+    pub fn create_tables(&self) -> Result<()> {
+        CONNECTION.with(|connection| {
+            if let Some(ref connection) = *connection.borrow() {
+                connection.execute("
+                CREATE TABLE IF NOT EXISTS history
+                ( id INTEGER PRIMARY KEY
+                , title TEXT NOT NULL
+                , url TEXT NOT NULL UNIQUE
+                , visit_count INTEGER NOT NULL DEFAULT 0
+                , last_visit INTEGER NOT NULL
+                )", [])?;
+
+                connection.execute("
+                CREATE INDEX IF NOT EXISTS history_last_visit
+                ON history (last_visit)
+                ", [])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Delete the history entry with the specified `url`.
+    pub fn delete(&self, url: &str) -> Result<()> {
+        CONNECTION.with(|connection| {
+            if let Some(ref connection) = *connection.borrow() {
+                connection.execute("
+                    DELETE FROM history
+                    WHERE url = $1
+                    ", &[&url.to_string()])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Query the history entries containing all the `words` in their title or URL.
+    /// The entries are sorted by frecency.
+    pub fn query(&self, words: &[String]) -> Vec<HistoryEntry> {
+        CONNECTION.with(|connection| {
+            if let Some(ref connection) = *connection.borrow() {
+                let mut params: Vec<&dyn ToSql> = vec![];
+
+                let mut title_idents = vec![];
+                for word in words {
+                    let index = params.len() + 1;
+                    title_idents.push(format!("(title LIKE '%' || ${} || '%' OR url LIKE '%' || ${} || '%')", index, index));
+                    params.push(word);
+                }
+                let title_idents = title_idents.join(" AND ");
+                let where_clause =
+                    if !title_idents.is_empty() {
+                        format!("WHERE {}", title_idents)
+                    }
+                    else {
+                        String::new()
+                    };
+
+                // The frecency gives more weight to the recent visits, similarly to what Firefox
+                // does.
+                if let Ok(mut statement) = connection.prepare(&format!("
+                            SELECT title, url, visit_count *
+                                CASE
+                                    WHEN last_visit >= strftime('%s', 'now') - 4 * 86400 THEN 100
+                                    WHEN last_visit >= strftime('%s', 'now') - 14 * 86400 THEN 70
+                                    WHEN last_visit >= strftime('%s', 'now') - 31 * 86400 THEN 50
+                                    WHEN last_visit >= strftime('%s', 'now') - 90 * 86400 THEN 30
+                                    ELSE 10
+                                END AS frecency
+                            FROM history
+                            {}
+                            ORDER BY frecency DESC, last_visit DESC
+                            LIMIT {}
+                        ", where_clause, QUERY_LIMIT))
+                {
+                    if let Ok(rows) = statement.query_map(&*params, |row| {
+                        Ok(HistoryEntry {
+                            title: row.get(0)?,
+                            url: row.get(1)?,
+                            frecency: row.get(2)?,
+                        })
+                    })
+                    {
+                        return rows.collect::<result::Result<Vec<_>, _>>().unwrap_or_else(|_| vec![]);
+                    }
+                }
+            }
+            vec![]
+        })
+    }
+
+    /// Set the title of the page at `url` without counting a new visit.
+    pub fn set_title(&self, url: &str, title: &str) -> Result<()> {
+        CONNECTION.with(|connection| {
+            if let Some(ref connection) = *connection.borrow() {
+                connection.execute("
+                    UPDATE history
+                    SET title = $1
+                    WHERE url = $2
+                    ", &[&title.to_string(), &url.to_string()])?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::{CONNECTION, HistoryManager, HistoryRange};
+
+    fn manager() -> HistoryManager {
+        // Each test runs in its own thread, so it gets its own connection.
+        CONNECTION.with(|connection| {
+            *connection.borrow_mut() = Some(Connection::open_in_memory().expect("in-memory database"));
+        });
+        let manager = HistoryManager::new();
+        manager.create_tables().expect("create tables");
+        manager
+    }
+
+    #[test]
+    fn add_query_delete() {
+        let manager = manager();
+        manager.add_visit("https://www.rust-lang.org/", Some("Rust".to_string())).unwrap();
+        manager.add_visit("https://example.com/", Some("Example".to_string())).unwrap();
+        manager.add_visit("https://example.com/", None).unwrap();
+
+        let entries = manager.query(&[]);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].url, "https://example.com/");
+        assert_eq!(entries[0].title, "Example");
+        assert_eq!(entries[0].frecency, 200);
+        assert_eq!(entries[1].url, "https://www.rust-lang.org/");
+        assert_eq!(entries[1].frecency, 100);
+
+        let entries = manager.query(&["rust".to_string()]);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].title, "Rust");
+
+        let entries = manager.query(&["example".to_string(), "rust".to_string()]);
+        assert!(entries.is_empty());
+
+        manager.delete("https://example.com/").unwrap();
+        let entries = manager.query(&[]);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].url, "https://www.rust-lang.org/");
+
+        manager.clear(HistoryRange::All).unwrap();
+        assert!(manager.query(&[]).is_empty());
+    }
+
+    #[test]
+    fn parse_range() {
+        assert!(matches!(HistoryRange::parse("all"), Ok(HistoryRange::All)));
+        assert!(matches!(HistoryRange::parse("last hour"), Ok(HistoryRange::LastHour)));
+        assert!(matches!(HistoryRange::parse("last-hour"), Ok(HistoryRange::LastHour)));
+        assert!(matches!(HistoryRange::parse("hour"), Ok(HistoryRange::LastHour)));
+        assert!(matches!(HistoryRange::parse("today"), Ok(HistoryRange::Today)));
+        assert!(HistoryRange::parse("yesterday").is_err());
+        assert!(HistoryRange::parse("last").is_err());
+        assert!(HistoryRange::parse("").is_err());
+    }
+}
@@ -34,15 +34,6 @@
  * Could it be due to using an old webkit2gtk?
  * It is not due to my web extension.
  *
- * TODO: add cosmetic filter support in adblocker: https://github.com/dudik/blockit
- * (doc: https://github.com/brave/adblock-rust/issues/152#issuecomment-771259069)
- * (that probably requires a MutationObserver: https://github.com/brave/adblock-rust/issues/152#issuecomment-771046487)
- *
- * FIXME: the adblocker seems to block good URL like https://blog.mozilla.org/security/2021/01/26/supercookie-protections/
- * TODO: add cosmetic filter support in adblocker: https://github.com/dudik/blockit
- * (doc: https://github.com/brave/adblock-rust/issues/152#issuecomment-771259069
- * (that probably requires a MutationObserver: https://github.com/brave/adblock-rust/issues/152#issuecomment-771046487)
- *
  * FIXME: auto-login on https://www.iga.net/fr/mon_compte/se_connecter
  *
  * FIXME: many popups in alibaba.com/google.com.
@@ -58,8 +49,6 @@
  *
  * TODO: disable youtube auto-play.
  *
- * TODO: update the easylist instead of the former hosts-based lists.
- *
  * TODO: this URL (https://smallbusiness.chron.com/hide-things-certain-people-facebook-29815.html)
  * freezes the window.
  *
@@ -67,8 +56,6 @@
  *
  * TODO: add shortcut to go to next input field?
  *
- * FIXME: pages full on links freeze when using follow.
- *
  * TODO: add shortcut to scroll by paragraph (like { and } in vim).
  *
  * FIXME: enabling webkit-mediasource breaks some youtube videos.
@@ -82,7 +69,6 @@
  * normal video: videoplayback?clen=26562312&itag=43&mime=video%2Fwebm&gir=yes&key=yt6&mv=m&c=web&ei=yE8ZW--bBoqH-QOR7ZjIAw&initcwndbps=867500&lmt=1516735696905984&mm=31,29&mn=sn-4pcgxovpgx-t0ae,sn-t0a7sn7d&id=o-AOM1kOpaVnUns1y_vIkM0Xxe-TssoxptbMiah5HPg
  * ads: videoplayback?ip=70.35.215.109&lmt=1526332905241335&itag=43&requiressl=yes&id=o-AICQD28nyUKvGo_q-p47C57Yq_MvY3o78bQ3yYn1nVFu&pcm2cms=yes&source=youtube&dur=0.000&key=yt6&mn=sn-4pcgxovpgx-t0ae,sn-t0a7sn7d&mm=31,29&pl=25&mv=m&mt=152838538
  *
- * FIXME: Submit login form does not work on Zoho and Grafana.
  * FIXME: URLs not opening in new window on http://www.canadiantire.ca/fr/outdoor-living/outdoor-power-equipment/lawn-mowers/electric-lawn-mowers.html?adlocation=LIT_Content_Category_ElectricMower_fr
  * * https://www.homedepot.ca/fr/accueil/categories/decor/meubles/mobilier-de-salle-de-sejour/tables-basses-et-tables-de-bout.html
  * * https://www.kijiji.ca/b-longueuil-rive-sud/table-basse/k0l1700279?dc=true
@@ -97,8 +83,6 @@
  *
  * TODO: might find this useful for for filler: https://webkitgtk.org/reference/webkit2gtk/stable/WebKitWebPage.html#WebKitWebPage-form-controls-associated
  *
- * FIXME: submit not working in bnc.ca login form.
- *
  * FIXME: cannot follow the clone button on GitHub anymore.
  *
  * FIXME: Hint on wrong position on: http://www.travbuddy.com/search_google.php?cx=001087027826441394888%3Ap6zx7j2vnt8&cof=FORID%3A9&q=test (now a scroll issue)
@@ -135,11 +119,6 @@
  * TODO: exit insert mode after hitting Enter in text input.
  * TODO: default window size.
  *
- * FIXME: config file marks not created.
- *
- * TODO: save mark when focusing the first input.
- * TODO: save current scroll position in ' register before starting a search.
- *
  * FIXME: using Escape in insert mode triggers Escape in the web page (in Scala doc: http://www.scala-lang.org/api/current/).
  *
  * TODO: plugin for a read mode (remove all useless stuff in the page, only keep the text).
@@ -149,7 +128,6 @@
  * TODO: show an error when there are no hints.
  *
  * TODO: handle network errors.
- * TODO: show an error for request blocked by host blocker (instead of a white page).
  *
  * TODO: ask confirmation before submitting again the same form.
  *
@@ -174,8 +152,6 @@
  *
  * FIXME: ctrl-/ should not trigger the mapping for /.
  *
- * TODO: allow using Backspace to remove the last hint character.
- *
  * TODO: remove ads on DuckDuckGo Lite.
  *
  * TODO: Command to know which pages are in which process:
@@ -194,11 +170,8 @@
  *
  * TODO: modal dialog for authentication.
  *
- * TODO: add a command to delete history, …
- *
  * TODO: show the letters typed in follow mode.
  *
- * TODO: command to restore the last closed window.
  * TODO: command to open last deleted bookmark?
  *
  * TODO: rename the quit command to close.
@@ -210,8 +183,6 @@
  * TODO: should the private context be cleaned up when all the private windows are closed?
  * TODO: should there be a new private context every time the command private-win-open is issued?
  *
- * TODO: ResetMarks.
- *
  * TODO: hide hovered link when the text entry is shown? (Or show the hovered URL instead of the
  * current URL?)
  * TODO: bigger text entry than URL label.
@@ -219,9 +190,6 @@
  * FIXME: should not silently fail when an included file is missing.
  *
  * TODO: webkit_web_view_get_main_resource() to get source code
- * TODO: Show hints on elements with an ID (to be able to navigate to their anchor).
- *
- * FIXME: angular form needs the typing action to be done in order to submit: https://www.codingame.com/start
  *
  * FIXME: seems slower when running as normal user (and faster as root), so perhaps the config slow
  * it down. Looks like it is slowed down by the hard drive.
@@ -239,9 +207,6 @@
  * FIXME: windows opened by JavaScript cannot be claused: probably need to set the settings
  * javascript_can_close_windows when the window was opened by JS.
  *
- * TODO: websites with login form (or credit card input) should be shown as insecure if not in
- * HTTPS.
- *
  * FIXME: scroll on
  * https://tutorial.ponylang.org/getting-started/how-it-works.html
  * https://www.fstar-lang.org/tutorial/
@@ -394,12 +359,15 @@
     unused_qualifications,
 )]
 
+extern crate adblock;
 extern crate cairo;
 extern crate gdk;
 extern crate gio;
 extern crate glib;
 extern crate gtk;
 extern crate gumdrop;
+extern crate hmac;
+extern crate keepass;
 #[cfg(test)]
 extern crate libxdo;
 #[macro_use]
@@ -413,11 +381,15 @@ extern crate mg_settings_macros;
 extern crate number_prefix;
 extern crate open;
 extern crate password_store;
+extern crate rand;
 #[macro_use]
 extern crate relm;
 #[macro_use]
 extern crate relm_derive;
 extern crate rusqlite;
+extern crate rustc_serialize;
+extern crate sha1;
+extern crate sha2;
 extern crate simplelog;
 extern crate syslog;
 extern crate tempfile;
@@ -425,9 +397,10 @@ extern crate titanium_common;
 extern crate url;
 extern crate webkit2gtk;
 extern crate xdg;
-extern crate zip;
 
+mod adblock_manager;
 mod app;
+mod bookmark_file;
 mod bookmarks;
 mod clipboard;
 mod commands;
@@ -438,12 +411,16 @@ mod download_view;
 mod download_list_view;
 mod errors;
 mod file;
+mod history;
+mod html;
 mod message_server;
 mod pass_manager;
 mod permission_manager;
 mod popup_manager;
+mod session;
 mod settings;
 mod stylesheet;
+mod totp;
 mod urls;
 mod webview;
 
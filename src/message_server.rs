@@ -21,57 +21,119 @@
 
 //! Message server interface.
 
-use std::collections::BTreeSet;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::io::{self, Write};
 use std::process;
+use std::rc::Rc;
 
 use gio::ApplicationHoldGuard;
+use gio::traits::ApplicationExt;
+use glib::Continue;
+use glib::source::unix_signal_add_local;
 use gtk::Application;
 use gtk::traits::GtkApplicationExt;
 use gtk::{
-    traits::DialogExt,
+    traits::{DialogExt, GtkWindowExt},
     ButtonsType,
     DialogFlags,
     MessageDialog,
     MessageType,
     Window,
 };
-use relm::{Component, EventStream, Relm, Update, UpdateNew, execute, init};
+use relm::{Channel, Component, EventStream, Relm, Update, UpdateNew, execute, init};
 use webkit2gtk::WebContext;
 
-use app::App;
+use titanium_common::InnerMessage;
+use titanium_common::InnerMessage::ReloadAdblockLists;
+
+use adblock_manager::{is_adblock_cache_stale, spawn_adblock_compilation};
+
+use app::{self, App};
 use app::Msg::{
+    BroadcastMessage,
     ChangeUrl,
+    CreateBackgroundWindow,
     CreateWindow,
+    LoadSession,
     Remove,
+    ReopenWindow,
+    SaveSession,
+    SendMessage,
+    SendWindowSession,
+    ShowError,
+    ShowInfo,
+    WebViewClose,
+    WindowClosed,
+    WindowSessionChanged,
+    WindowSessionSent,
 };
 use config_dir::ConfigDir;
 use errors::Result;
 use self::Msg::*;
+use session::{self, AUTOSAVE_SESSION, Session, WindowSession};
 use webview::WebView;
 
+/// The maximum number of closed windows that can be reopened.
+const CLOSED_WINDOW_LIMIT: usize = 20;
+
+// The signals sent when the session ends or the system shuts down (these numbers are the same on
+// all Unix systems).
+const SIGHUP: i32 = 1;
+const SIGINT: i32 = 2;
+const SIGTERM: i32 = 15;
+const QUIT_SIGNALS: [i32; 3] = [SIGHUP, SIGINT, SIGTERM];
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum Privacy {
     Normal,
     Private,
 }
 
+/// A closed window that can be reopened.
+pub struct ClosedWindow {
+    pub privacy: Privacy,
+    pub session: WindowSession,
+}
+
+/// The closed windows, the most recent first.
+/// It is shared with the windows to show it in the completions.
+pub type ClosedWindows = Rc<RefCell<VecDeque<ClosedWindow>>>;
+
 pub struct MessageServer {
     model: Model,
 }
 
+/// A session being saved, waiting for the state of the windows.
+struct SessionRequest {
+    name: String,
+    remaining_windows: BTreeSet<usize>,
+    requester: usize,
+    windows: BTreeMap<usize, WindowSession>,
+}
+
 pub struct Model {
+    adblock_compilation: Option<Channel<Result<()>>>,
     app_count: usize,
     application: Application,
     app_hold_guard: Option<ApplicationHoldGuard>,
+    /// The state of the windows, saved in the autosave session.
+    autosaved_windows: BTreeMap<usize, WindowSession>,
+    closed_windows: ClosedWindows,
     config_dir: ConfigDir,
     /// This listener is used to prevent two instances of Titanium to run at the same time.
     private_web_context: WebContext,
     opened_urls: BTreeSet<String>,
+    /// The indices in `wins` of the windows that are still opened.
+    opened_wins: BTreeSet<usize>,
     previous_opened_urls: BTreeSet<String>,
+    /// Whether all the windows are being closed, in which case they are kept in the autosave
+    /// session.
+    quitting: bool,
     relm: Relm<MessageServer>,
+    session_request: Option<SessionRequest>,
     // TODO: save the widgets somewhere allowing to remove them when its window is closed.
     wins: Vec<Component<App>>,
     web_context: WebContext,
@@ -79,10 +141,20 @@ pub struct Model {
 
 #[derive(Msg)]
 pub enum Msg {
+    AdblockListsCompiled(Result<()>),
+    AddClosedWindow(WindowSession, Privacy),
+    AddWindowSession(usize, Option<WindowSession>),
+    AutosaveWindowSession(usize, WindowSession),
+    Broadcast(usize, InnerMessage),
     ChangeOpenedPage(String, String),
+    LoadSessionWindows(usize, String),
     NewApp(Option<String>, Privacy),
+    NewBackgroundApp(usize, String, Privacy),
+    Quit,
     ReleaseApp,
-    RemoveApp(String),
+    RemoveApp(usize, String),
+    ReopenClosedWindow(usize, Option<String>),
+    SaveSessionWindows(usize, String),
 }
 
 impl Update for MessageServer {
@@ -93,6 +165,7 @@ impl Update for MessageServer {
     fn model(relm: &Relm<Self>, (application, app_hold_guard, urls, config): (Application, Option<ApplicationHoldGuard>, Vec<String>, Option<String>)) -> Model {
         let config_dir = ConfigDir::new(&config).unwrap(); // TODO: remove unwrap().
         let (web_context, private_web_context) = WebView::initialize_web_extension(&config_dir);
+        let adblock_compilation = MessageServer::compile_stale_adblock_lists(relm, &config_dir);
         if urls.is_empty() {
             relm.stream().emit(NewApp(None, Privacy::Normal));
             relm.stream().emit(ReleaseApp);
@@ -104,31 +177,69 @@ impl Update for MessageServer {
             relm.stream().emit(ReleaseApp);
         }
         Model {
+            adblock_compilation,
             app_count: 0,
             application,
             app_hold_guard,
+            autosaved_windows: BTreeMap::new(),
+            closed_windows: Rc::new(RefCell::new(VecDeque::new())),
             config_dir,
             opened_urls: BTreeSet::new(),
+            opened_wins: BTreeSet::new(),
             previous_opened_urls: BTreeSet::new(),
             private_web_context,
+            quitting: false,
             relm: relm.clone(),
+            session_request: None,
             wins: vec![],
             web_context,
         }
     }
 
+    fn subscriptions(&mut self, relm: &Relm<Self>) {
+        // Close the windows properly when the session ends, so that they are all saved in the
+        // autosave session.
+        for &signal in &QUIT_SIGNALS {
+            let stream = relm.stream().clone();
+            unix_signal_add_local(signal, move || {
+                stream.emit(Quit);
+                Continue(true)
+            });
+        }
+    }
+
     fn update(&mut self, event: Msg) {
         match event {
+            AdblockListsCompiled(result) => self.adblock_lists_compiled(result),
+            AddClosedWindow(session, privacy) => self.add_closed_window(session, privacy),
+            AddWindowSession(index, session) => self.add_window_session(index, session),
+            AutosaveWindowSession(index, session) => {
+                // A window can send its state after it is closed when its autosave was delayed.
+                if self.model.opened_wins.contains(&index) {
+                    self.model.autosaved_windows.insert(index, session);
+                    self.autosave_session();
+                }
+            },
+            Broadcast(sender, message) => self.broadcast(sender, message),
             ChangeOpenedPage(old, new) => {
                 self.model.opened_urls.remove(&old);
                 self.model.opened_urls.insert(new);
                 self.save_urls();
             },
-            NewApp(url, privacy) => self.add_app(url, privacy),
+            LoadSessionWindows(requester, name) => self.load_session(requester, &name),
+            NewApp(url, privacy) => self.add_app(url, privacy, None),
+            NewBackgroundApp(requester, url, privacy) => {
+                self.add_app(Some(url), privacy, None);
+                // Give the focus back to the window that opened the new one.
+                self.model.wins[requester].widget().present();
+            },
+            Quit => self.quit(),
             // NOTE: we called hold() on the application in order to create the window
             // asynchronously. Now that it is created, we can call release().
             ReleaseApp => { self.model.app_hold_guard.take(); },
-            RemoveApp(url) => self.remove_app(url),
+            RemoveApp(index, url) => self.remove_app(index, url),
+            ReopenClosedWindow(requester, url) => self.reopen_closed_window(requester, url),
+            SaveSessionWindows(requester, name) => self.save_session(requester, name),
         }
     }
 }
@@ -146,7 +257,20 @@ impl MessageServer {
         Ok(execute::<MessageServer>((application, app_hold_guard, url, config_dir)))
     }
 
-    fn add_app(&mut self, url: Option<String>, privacy: Privacy) {
+    /// Tell the web processes to reload the filter lists, now that the engine cache is compiled.
+    fn adblock_lists_compiled(&mut self, result: Result<()>) {
+        self.model.adblock_compilation = None;
+        match result {
+            Ok(()) => {
+                for &index in &self.model.opened_wins {
+                    self.model.wins[index].emit(SendMessage(ReloadAdblockLists()));
+                }
+            },
+            Err(error) => error!("Cannot compile the adblock lists: {}", error),
+        }
+    }
+
+    fn add_app(&mut self, url: Option<String>, privacy: Privacy, session: Option<WindowSession>) {
         self.model.app_count += 1;
         let web_context =
             if privacy == Privacy::Private {
@@ -163,12 +287,160 @@ impl MessageServer {
             self.save_urls();
         }
 
-        let app = init::<App>((url, self.model.config_dir.clone(), web_context, self.model.previous_opened_urls.clone())).unwrap(); // TODO: remove unwrap().
+        let app = init::<App>((url, self.model.config_dir.clone(), web_context, self.model.previous_opened_urls.clone(),
+            session, self.model.closed_windows.clone())).unwrap(); // TODO: remove unwrap().
         self.model.application.add_window(app.widget());
+        let index = self.model.wins.len();
+        connect!(app@CreateBackgroundWindow(ref url, ref privacy), self.model.relm,
+            NewBackgroundApp(index, url.clone(), *privacy));
         connect!(app@CreateWindow(ref url, ref privacy), self.model.relm, NewApp(Some(url.clone()), *privacy));
-        connect!(app@Remove(ref url), self.model.relm, RemoveApp(url.clone()));
+        connect!(app@Remove(ref url), self.model.relm, RemoveApp(index, url.clone()));
+        connect!(app@BroadcastMessage(ref message), self.model.relm, Broadcast(index, message.clone()));
         connect!(app@ChangeUrl(ref old, ref new), self.model.relm, ChangeOpenedPage(old.clone(), new.clone()));
+        connect!(app@LoadSession(ref name), self.model.relm, LoadSessionWindows(index, name.clone()));
+        connect!(app@SaveSession(ref name), self.model.relm, SaveSessionWindows(index, name.clone()));
+        connect!(app@WindowClosed(ref session, ref privacy), self.model.relm,
+            AddClosedWindow(session.clone(), *privacy));
+        connect!(app@ReopenWindow(ref url), self.model.relm, ReopenClosedWindow(index, url.clone()));
+        connect!(app@WindowSessionChanged(ref session), self.model.relm,
+            AutosaveWindowSession(index, session.clone()));
+        connect!(app@WindowSessionSent(ref session), self.model.relm, AddWindowSession(index, session.clone()));
         self.model.wins.push(app);
+        self.model.opened_wins.insert(index);
+    }
+
+    /// Add a window to the closed windows, forgetting the oldest one when there are too many.
+    fn add_closed_window(&mut self, session: WindowSession, privacy: Privacy) {
+        let mut closed_windows = self.model.closed_windows.borrow_mut();
+        closed_windows.push_front(ClosedWindow {
+            privacy,
+            session,
+        });
+        closed_windows.truncate(CLOSED_WINDOW_LIMIT);
+    }
+
+    /// Add the state of a window to the session being saved and save it when all the windows
+    /// sent their state.
+    fn add_window_session(&mut self, index: usize, session: Option<WindowSession>) {
+        let finished =
+            if let Some(ref mut request) = self.model.session_request {
+                if let Some(session) = session {
+                    request.windows.insert(index, session);
+                }
+                request.remaining_windows.remove(&index);
+                request.remaining_windows.is_empty()
+            }
+            else {
+                false
+            };
+        if finished {
+            if let Some(request) = self.model.session_request.take() {
+                let window_count = request.windows.len();
+                let session = Session {
+                    windows: request.windows.into_iter().map(|(_, window)| window).collect(),
+                };
+                let message =
+                    match session::save(&self.model.config_dir, &request.name, &session) {
+                        Ok(()) => ShowInfo(format!("Saved session {} ({} windows)", request.name, window_count)),
+                        Err(error) => ShowError(error.to_string()),
+                    };
+                self.send_to_window(request.requester, message);
+            }
+        }
+    }
+
+    /// Save the state of the opened windows in the autosave session.
+    fn autosave_session(&self) {
+        let session = Session {
+            windows: self.model.autosaved_windows.values().cloned().collect(),
+        };
+        if let Err(error) = session::save(&self.model.config_dir, AUTOSAVE_SESSION, &session) {
+            error!("Cannot autosave the session: {}", error);
+        }
+    }
+
+    /// Send the message to the web process of every window except the sender.
+    fn broadcast(&self, sender: usize, message: InnerMessage) {
+        for &index in &self.model.opened_wins {
+            if index != sender {
+                self.model.wins[index].emit(SendMessage(message.clone()));
+            }
+        }
+    }
+
+    /// Compile the adblock filter lists in the background if they changed since the last compilation
+    /// (e.g. after editing a list of the config), so that the web processes do not have to parse
+    /// them.
+    fn compile_stale_adblock_lists(relm: &Relm<Self>, config_dir: &ConfigDir) -> Option<Channel<Result<()>>> {
+        match is_adblock_cache_stale(config_dir) {
+            Ok(true) => (),
+            Ok(false) => return None,
+            Err(error) => {
+                error!("Cannot check the adblock engine cache: {}", error);
+                return None;
+            },
+        }
+        let stream = relm.stream().clone();
+        match spawn_adblock_compilation(config_dir, move |result| stream.emit(AdblockListsCompiled(result))) {
+            Ok(channel) => Some(channel),
+            Err(error) => {
+                error!("Cannot compile the adblock lists: {}", error);
+                None
+            },
+        }
+    }
+
+    /// Open the windows of the session `name`.
+    fn load_session(&mut self, requester: usize, name: &str) {
+        match session::load(&self.model.config_dir, name) {
+            Ok(session) => {
+                for window in session.windows {
+                    self.add_app(Some(window.url.clone()), Privacy::Normal, Some(window));
+                }
+            },
+            Err(error) => self.send_to_window(requester, ShowError(error.to_string())),
+        }
+    }
+
+    /// Reopen the most recently closed window, or the most recent one with the specified `url`.
+    fn reopen_closed_window(&mut self, requester: usize, url: Option<String>) {
+        let closed_window = {
+            let mut closed_windows = self.model.closed_windows.borrow_mut();
+            let position =
+                match url {
+                    Some(url) => closed_windows.iter().position(|window| window.session.url == url),
+                    None => Some(0),
+                };
+            position.and_then(|position| closed_windows.remove(position))
+        };
+        match closed_window {
+            Some(window) =>
+                self.add_app(Some(window.session.url.clone()), window.privacy, Some(window.session)),
+            None => self.send_to_window(requester, ShowError("No closed window to reopen".to_string())),
+        }
+    }
+
+    /// Ask all the opened windows for their state in order to save the session `name`.
+    fn save_session(&mut self, requester: usize, name: String) {
+        if self.model.session_request.is_some() {
+            self.send_to_window(requester, ShowError("A session is already being saved".to_string()));
+            return;
+        }
+        self.model.session_request = Some(SessionRequest {
+            name,
+            remaining_windows: self.model.opened_wins.clone(),
+            requester,
+            windows: BTreeMap::new(),
+        });
+        for &index in &self.model.opened_wins {
+            self.model.wins[index].emit(SendWindowSession);
+        }
+    }
+
+    fn send_to_window(&self, index: usize, message: app::Msg) {
+        if self.model.opened_wins.contains(&index) {
+            self.model.wins[index].emit(message);
+        }
     }
 
     fn load_opened_urls(&mut self) {
@@ -189,16 +461,39 @@ impl MessageServer {
         }
     }
 
-    fn remove_app(&mut self, url: String) {
+    /// Close all the windows, without asking to cancel the downloads, and quit the application.
+    fn quit(&mut self) {
+        if self.model.quitting {
+            return;
+        }
+        self.model.quitting = true;
+        if self.model.opened_wins.is_empty() {
+            self.model.application.quit();
+        }
+        for &index in &self.model.opened_wins {
+            self.model.wins[index].emit(WebViewClose);
+        }
+    }
+
+    fn remove_app(&mut self, index: usize, url: String) {
         self.model.opened_urls.remove(&url);
         self.save_urls();
 
         self.model.app_count -= 1;
+        self.model.opened_wins.remove(&index);
         // TODO: remove from self.model.wins.
         if self.model.app_count == 0 {
             self.model.opened_urls.clear();
             self.save_urls();
         }
+        // Keep the last window in the autosave session, so that it can be restored after quitting.
+        // Also keep all the windows when quitting: only a window closed by the user is removed.
+        else if !self.model.quitting && self.model.autosaved_windows.remove(&index).is_some() {
+            self.autosave_session();
+        }
+
+        // The closed window will not send its state anymore.
+        self.add_window_session(index, None);
     }
 
     fn save_urls(&self) {
@@ -0,0 +1,103 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Generation of random passwords.
+
+use rand::Rng;
+use rand::rngs::OsRng;
+
+use errors::{Error, Result};
+
+const DEFAULT_CHARSET: &str = "all";
+const DEFAULT_LENGTH: usize = 20;
+const DIGITS: &str = "0123456789";
+/// Prefix of the charset argument giving the list of characters to use.
+const LITERAL_CHARSET_PREFIX: &str = "chars:";
+const LOWERCASE_LETTERS: &str = "abcdefghijklmnopqrstuvwxyz";
+const SYMBOLS: &str = "!#$%&()*+,-./:;<=>?@[]^_{|}~";
+const UPPERCASE_LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Get the characters of the charset `name`, which is either a known charset or `chars:` followed
+/// by the list of characters.
+fn charset(name: &str) -> Result<Vec<char>> {
+    let characters =
+        match name {
+            "all" => format!("{}{}{}{}", LOWERCASE_LETTERS, UPPERCASE_LETTERS, DIGITS, SYMBOLS),
+            "alnum" => format!("{}{}{}", LOWERCASE_LETTERS, UPPERCASE_LETTERS, DIGITS),
+            "alpha" => format!("{}{}", LOWERCASE_LETTERS, UPPERCASE_LETTERS),
+            "digits" => DIGITS.to_string(),
+            "hex" => "0123456789abcdef".to_string(),
+            _ if name.starts_with(LITERAL_CHARSET_PREFIX) => name[LITERAL_CHARSET_PREFIX.len()..].to_string(),
+            _ => return Err(Error::from_string(format!("Unknown charset: {} (use chars:{} to use these characters)",
+                name, name))),
+        };
+    if characters.is_empty() {
+        return Err(Error::new("The charset is empty"));
+    }
+    let mut characters: Vec<_> = characters.chars().collect();
+    characters.sort();
+    characters.dedup();
+    Ok(characters)
+}
+
+/// Generate a random password from the arguments `[length] [charset]` where charset is either
+/// all, alnum, alpha, digits, hex or chars: followed by the list of characters to use.
+pub fn generate_password(args: &str) -> Result<String> {
+    let mut args = args.split_whitespace();
+    let length =
+        match args.next() {
+            Some(length) => length.parse()
+                .map_err(|_| Error::from_string(format!("Invalid password length: {}", length)))?,
+            None => DEFAULT_LENGTH,
+        };
+    if length == 0 {
+        return Err(Error::new("The password length must be greater than 0"));
+    }
+    let characters = charset(args.next().unwrap_or(DEFAULT_CHARSET))?;
+    let mut rng = OsRng;
+    Ok((0..length)
+        .map(|_| characters[rng.gen_range(0..characters.len())])
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DIGITS, generate_password};
+
+    #[test]
+    fn generate() {
+        let password = generate_password("").ok().unwrap();
+        assert_eq!(password.chars().count(), 20);
+
+        let password = generate_password("32 digits").ok().unwrap();
+        assert_eq!(password.len(), 32);
+        assert!(password.chars().all(|character| DIGITS.contains(character)));
+
+        let password = generate_password("10 chars:ab").ok().unwrap();
+        assert_eq!(password.len(), 10);
+        assert!(password.chars().all(|character| character == 'a' || character == 'b'));
+
+        assert!(generate_password("0").is_err());
+        assert!(generate_password("ten").is_err());
+        assert!(generate_password("10 alphanum").is_err());
+        assert!(generate_password("10 chars:").is_err());
+    }
+}
@@ -0,0 +1,449 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Password backend using a KeePass database, like the ones of KeePassXC.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::SystemTime;
+
+use keepass::{Database, DatabaseKey};
+use keepass::db::{Entry, Group, Node, Value};
+use relm;
+use titanium_common::urls::host;
+
+use app::APP_NAME;
+use errors::{Error, Result};
+use super::{PasswordBackend, send_result};
+
+/// A function run by the thread owning a database.
+type Job = Box<dyn FnOnce(&mut Store) + Send>;
+
+thread_local! {
+    // The databases are unlocked once per session and shared by the windows.
+    static WORKERS: RefCell<HashMap<(PathBuf, String), Worker>> = RefCell::new(HashMap::new());
+}
+
+/// Backend storing the credentials in a KDBX file.
+/// The entries are looked up by their URL in every group and the new ones are added to the
+/// `titanium` group.
+/// The database is decrypted once in another thread, which then answers from memory.
+/// The modifications are saved in this thread without waiting, since encrypting is as slow as
+/// decrypting.
+pub struct KdbxBackend {
+    password_command: String,
+    path: PathBuf,
+    write: bool,
+}
+
+impl KdbxBackend {
+    /// Create a backend for the database at `path`, unlocked with the output of `password_command`.
+    /// The database is only modified if `write` is true, because the KeePass library may not save
+    /// everything it read (like the attachments or the data of the plugins).
+    pub fn new(path: &str, password_command: &str, write: bool) -> Self {
+        KdbxBackend {
+            password_command: password_command.to_string(),
+            path: PathBuf::from(path),
+            write,
+        }
+    }
+
+    fn check_write(&self) -> Result<()> {
+        if self.write {
+            Ok(())
+        }
+        else {
+            Err(Error::new("Modifying the KeePass database is disabled: keep a backup and set password-kdbx-write to true to enable it"))
+        }
+    }
+
+    /// Run `job` in the thread owning the database and wait for its result.
+    /// This is only used when the database is unlocked, since it is then answered from memory.
+    fn run<F, T>(&self, job: F) -> Result<T>
+        where F: FnOnce(&mut Store) -> Result<T> + Send + 'static,
+              T: Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        self.worker().send(Box::new(move |store| {
+            // The receiver is only dropped if the caller panicked.
+            let _ = sender.send(job(store));
+        }))?;
+        receiver.recv()
+            .map_err(|_| Error::new("The KeePass database thread stopped"))?
+    }
+
+    /// Run `job` in the thread owning the database and send its result to `sender`, without
+    /// waiting for it.
+    fn spawn<F, T>(&self, sender: relm::Sender<Result<T>>, job: F) -> Result<()>
+        where F: FnOnce(&mut Store) -> Result<T> + Send + 'static,
+              T: Send + 'static,
+    {
+        self.worker().send(Box::new(move |store| {
+            if let Err(error) = send_result(&sender, job(store)) {
+                error!("{}", error);
+            }
+        }))
+    }
+
+    /// Get the thread owning the database, starting it the first time.
+    fn worker(&self) -> Worker {
+        WORKERS.with(|workers| {
+            workers.borrow_mut()
+                .entry((self.path.clone(), self.password_command.clone()))
+                .or_insert_with(|| Worker::new(self.path.clone(), self.password_command.clone()))
+                .clone()
+        })
+    }
+}
+
+impl PasswordBackend for KdbxBackend {
+    fn delete(&self, host: &str, username: &str, sender: relm::Sender<Result<()>>) -> Result<()> {
+        self.check_write()?;
+        let host = host.to_string();
+        let username = username.to_string();
+        self.spawn(sender, move |store| {
+            if remove_entries(&mut store.database()?.root, &host, &username) {
+                store.save()?;
+            }
+            Ok(())
+        })
+    }
+
+    fn get(&self, host: &str, username: &str) -> Result<(String, String)> {
+        let host = host.to_string();
+        let username = username.to_string();
+        self.run(move |store| {
+            let database = store.database()?;
+            let mut entries = vec![];
+            find_entries(&database.root, &host, &mut entries);
+            entries.iter()
+                .find(|entry| entry.get_username() == Some(username.as_str()))
+                .map(|entry| (username.clone(), entry.get_password().unwrap_or_default().to_string()))
+                .ok_or_else(|| Error::from_string(format!("No password for {} on {}", username, host)))
+        })
+    }
+
+    fn get_otp_uri(&self, host: &str, username: &str) -> Result<String> {
+        let host = host.to_string();
+        let username = username.to_string();
+        self.run(move |store| {
+            let database = store.database()?;
+            let mut entries = vec![];
+            find_entries(&database.root, &host, &mut entries);
+            // KeePassXC saves the OTP URI in the otp attribute.
+            entries.iter()
+                .find(|entry| entry.get_username() == Some(username.as_str()))
+                .and_then(|entry| entry.get("otp"))
+                .map(ToString::to_string)
+                .ok_or_else(|| Error::from_string(format!("No OTP URI for {} on {}", username, host)))
+        })
+    }
+
+    fn get_usernames(&self, host: &str) -> Result<Vec<String>> {
+        let host = host.to_string();
+        self.run(move |store| {
+            let database = store.database()?;
+            let mut entries = vec![];
+            find_entries(&database.root, &host, &mut entries);
+            let mut usernames: Vec<String> = entries.iter()
+                .filter_map(|entry| entry.get_username())
+                .map(ToString::to_string)
+                .collect();
+            usernames.sort();
+            usernames.dedup();
+            Ok(usernames)
+        })
+    }
+
+    fn insert(&self, host: &str, username: &str, password: &str, sender: relm::Sender<Result<bool>>) -> Result<()> {
+        self.check_write()?;
+        let host = host.to_string();
+        let username = username.to_string();
+        let password = password.to_string();
+        self.spawn(sender, move |store| {
+            let updated = {
+                let database = store.database()?;
+                let updated = match find_entry_mut(&mut database.root, &host, &username) {
+                    Some(entry) => {
+                        entry.fields.insert("Password".to_string(), Value::Protected(password.as_str().into()));
+                        true
+                    },
+                    None => false,
+                };
+                if !updated {
+                    let mut entry = Entry::new();
+                    entry.fields.insert("Title".to_string(), Value::Unprotected(host.clone()));
+                    entry.fields.insert("URL".to_string(), Value::Unprotected(host.clone()));
+                    entry.fields.insert("UserName".to_string(), Value::Unprotected(username.clone()));
+                    entry.fields.insert("Password".to_string(), Value::Protected(password.as_str().into()));
+                    app_group(&mut database.root).children.push(Node::Entry(entry));
+                }
+                updated
+            };
+            store.save()?;
+            Ok(updated)
+        })
+    }
+
+    fn is_unlocked(&self) -> bool {
+        let worker = self.worker();
+        let mut status =
+            match worker.status.lock() {
+                Ok(status) => status,
+                Err(_) => return false,
+            };
+        // Reopening the database when another program modified it is as slow as unlocking it, so
+        // consider it locked to do it in the background.
+        if status.unlocked && modified_time(&self.path).ok() != Some(status.modified) {
+            status.unlocked = false;
+        }
+        status.unlocked
+    }
+
+    fn unlock(&self, sender: relm::Sender<Result<()>>) -> Result<()> {
+        self.spawn(sender, |store| store.database().map(|_| ()))
+    }
+}
+
+/// Whether the database is decrypted, shared by the thread owning it.
+#[derive(Default)]
+struct Status {
+    /// The modification time of the file that was decrypted.
+    modified: Option<SystemTime>,
+    unlocked: bool,
+}
+
+/// Handle to the thread owning a database.
+#[derive(Clone)]
+struct Worker {
+    jobs: Sender<Job>,
+    status: Arc<Mutex<Status>>,
+}
+
+impl Worker {
+    fn new(path: PathBuf, password_command: String) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let status = Arc::new(Mutex::new(Status::default()));
+        let mut store = Store {
+            database: None,
+            password_command,
+            path,
+            status: status.clone(),
+        };
+        thread::spawn(move || {
+            for job in receiver {
+                job(&mut store);
+            }
+        });
+        Worker {
+            jobs,
+            status,
+        }
+    }
+
+    fn send(&self, job: Job) -> Result<()> {
+        self.jobs.send(job)
+            .map_err(|_| Error::new("The KeePass database thread stopped"))
+    }
+}
+
+/// The decrypted database with the key to save it.
+struct UnlockedDatabase {
+    database: Database,
+    master_password: String,
+    modified: Option<SystemTime>,
+}
+
+/// The state of a database, only used in the thread owning it.
+struct Store {
+    database: Option<UnlockedDatabase>,
+    password_command: String,
+    path: PathBuf,
+    status: Arc<Mutex<Status>>,
+}
+
+impl Store {
+    /// Get the database, unlocking it the first time and reopening it when another program
+    /// modified the file.
+    fn database(&mut self) -> Result<&mut Database> {
+        let modified = modified_time(&self.path)?;
+        let up_to_date = self.database.as_ref()
+            .map(|unlocked| unlocked.modified == modified)
+            .unwrap_or(false);
+        if !up_to_date {
+            self.set_status(false, modified);
+            let master_password =
+                match self.database.take() {
+                    Some(unlocked) => unlocked.master_password,
+                    None => self.master_password()?,
+                };
+            let database = self.open(&master_password)?;
+            self.database = Some(UnlockedDatabase {
+                database,
+                master_password,
+                modified,
+            });
+        }
+        self.set_status(true, modified);
+        match self.database {
+            Some(ref mut unlocked) => Ok(&mut unlocked.database),
+            None => unreachable!(),
+        }
+    }
+
+    /// Run the password command to get the master password of the database.
+    fn master_password(&self) -> Result<String> {
+        if self.password_command.is_empty() {
+            return Err(Error::new("The setting password-kdbx-password-command is not set"));
+        }
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&self.password_command)
+            .output()?;
+        if !output.status.success() {
+            return Err(Error::from_string(format!("The command {} failed", self.password_command)));
+        }
+        let password = String::from_utf8(output.stdout)
+            .map_err(|_| Error::new("The output of the password command is not valid UTF-8"))?;
+        Ok(password.trim_end_matches('\n').to_string())
+    }
+
+    fn open(&self, password: &str) -> Result<Database> {
+        let mut file = File::open(&self.path)?;
+        Database::open(&mut file, DatabaseKey::new().with_password(password))
+            .map_err(|error| Error::from_string(format!("Cannot open the database {}: {}", self.path.display(), error)))
+    }
+
+    /// Save the database to a temporary file first to avoid corrupting it when the save fails.
+    fn save(&mut self) -> Result<()> {
+        if let Some(ref mut unlocked) = self.database {
+            let mut temp_path = self.path.clone().into_os_string();
+            temp_path.push(".tmp");
+            {
+                let mut file = File::create(&temp_path)?;
+                unlocked.database.save(&mut file, DatabaseKey::new().with_password(&unlocked.master_password))
+                    .map_err(|error| Error::from_string(format!("Cannot save the database {}: {}", self.path.display(), error)))?;
+            }
+            fs::rename(&temp_path, &self.path)?;
+            // Do not reopen the file that was just written.
+            unlocked.modified = modified_time(&self.path)?;
+        }
+        if let Some(modified) = self.database.as_ref().map(|unlocked| unlocked.modified) {
+            self.set_status(true, modified);
+        }
+        Ok(())
+    }
+
+    /// Share whether the version of the file modified at `modified` is decrypted.
+    fn set_status(&self, unlocked: bool, modified: Option<SystemTime>) {
+        if let Ok(mut status) = self.status.lock() {
+            *status = Status {
+                modified,
+                unlocked,
+            };
+        }
+    }
+}
+
+/// Get the group where the new entries are added, creating it if needed.
+fn app_group(root: &mut Group) -> &mut Group {
+    let index = root.children.iter()
+        .position(|node| match *node {
+            Node::Group(ref group) => group.name == APP_NAME,
+            Node::Entry(_) => false,
+        });
+    let index =
+        match index {
+            Some(index) => index,
+            None => {
+                root.children.push(Node::Group(Group::new(APP_NAME)));
+                root.children.len() - 1
+            },
+        };
+    match root.children[index] {
+        Node::Group(ref mut group) => group,
+        Node::Entry(_) => unreachable!(),
+    }
+}
+
+fn find_entries<'a>(group: &'a Group, host: &str, entries: &mut Vec<&'a Entry>) {
+    for node in &group.children {
+        match *node {
+            Node::Entry(ref entry) =>
+                if is_entry_for(entry, host) {
+                    entries.push(entry);
+                },
+            Node::Group(ref group) => find_entries(group, host, entries),
+        }
+    }
+}
+
+fn find_entry_mut<'a>(group: &'a mut Group, host: &str, username: &str) -> Option<&'a mut Entry> {
+    for node in &mut group.children {
+        match *node {
+            Node::Entry(ref mut entry) =>
+                if is_entry_for(entry, host) && entry.get_username() == Some(username) {
+                    return Some(entry);
+                },
+            Node::Group(ref mut group) =>
+                if let Some(entry) = find_entry_mut(group, host, username) {
+                    return Some(entry);
+                },
+        }
+    }
+    None
+}
+
+/// Check if the URL of the entry, which can be a bare host, is on `host`.
+fn is_entry_for(entry: &Entry, entry_host: &str) -> bool {
+    match entry.get_url() {
+        Some(url) => host(url).map(|url_host| url_host == entry_host).unwrap_or(false) || url == entry_host,
+        None => false,
+    }
+}
+
+fn modified_time(path: &Path) -> Result<Option<SystemTime>> {
+    if path.as_os_str().is_empty() {
+        return Err(Error::new("The setting password-kdbx-file is not set"));
+    }
+    Ok(fs::metadata(path)?.modified().ok())
+}
+
+fn remove_entries(group: &mut Group, host: &str, username: &str) -> bool {
+    let count = group.children.len();
+    group.children.retain(|node| match *node {
+        Node::Entry(ref entry) => !(is_entry_for(entry, host) && entry.get_username() == Some(username)),
+        Node::Group(_) => true,
+    });
+    let mut removed = group.children.len() != count;
+    for node in &mut group.children {
+        if let Node::Group(ref mut group) = *node {
+            removed |= remove_entries(group, host, username);
+        }
+    }
+    removed
+}
@@ -0,0 +1,200 @@
+/*
+ * Copyright (c) 2016-2018 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Password management.
+
+mod generator;
+mod kdbx;
+mod pass;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use relm::Sender;
+use titanium_common::urls::host;
+
+use errors::{Error, Result};
+use file;
+
+pub use self::generator::generate_password;
+pub use self::kdbx::KdbxBackend;
+pub use self::pass::PassBackend;
+
+/// A storage of credentials, indexed by host and username.
+pub trait PasswordBackend {
+    /// Delete the credentials of `username` for `host` and send the result to `sender`.
+    fn delete(&self, host: &str, username: &str, sender: Sender<Result<()>>) -> Result<()>;
+
+    /// Get the username and password of `username` for `host`.
+    fn get(&self, host: &str, username: &str) -> Result<(String, String)>;
+
+    /// Get the otpauth:// URI stored with the credentials of `username` for `host`.
+    fn get_otp_uri(&self, host: &str, username: &str) -> Result<String>;
+
+    /// Get the usernames having credentials for `host`.
+    fn get_usernames(&self, host: &str) -> Result<Vec<String>>;
+
+    /// Save the password of `username` for `host`, replacing the existing one.
+    /// Send to `sender` whether a password was replaced.
+    fn insert(&self, host: &str, username: &str, password: &str, sender: Sender<Result<bool>>) -> Result<()>;
+
+    /// Check if the backend can be used without waiting for it to be unlocked.
+    fn is_unlocked(&self) -> bool {
+        true
+    }
+
+    /// Unlock the backend in the background and send the result to `sender`.
+    fn unlock(&self, sender: Sender<Result<()>>) -> Result<()> {
+        send_result(&sender, Ok(()))
+    }
+}
+
+/// A password manager is used to add, get and remove credentials.
+pub struct PasswordManager {
+    backend: Box<dyn PasswordBackend>,
+    last_usernames_path: Option<PathBuf>,
+}
+
+impl PasswordManager {
+    /// Create a new password manager, using `pass` as its backend.
+    /// The last username used for each host is saved in the file `last_usernames_path`.
+    pub fn new(last_usernames_path: Option<PathBuf>) -> Self {
+        PasswordManager {
+            backend: Box::new(PassBackend),
+            last_usernames_path,
+        }
+    }
+
+    /// Add a credential, updating the password if the username already exists.
+    /// Send to `sender` whether the credential was updated.
+    pub fn add(&self, url: &str, username: &str, password: &str, sender: Sender<Result<bool>>) -> Result<()> {
+        if let Some(url) = host(url) {
+            self.backend.insert(&url, username, password, sender)
+        }
+        else {
+            Err(Error::from_string(format!("Not adding the credentials for {}", url)))
+        }
+    }
+
+    /// Delete a password and send the result to `sender`.
+    pub fn delete(&self, url: &str, username: &str, sender: Sender<Result<()>>) -> Result<()> {
+        if let Some(url) = host(url) {
+            self.backend.delete(&url, username, sender)?;
+        }
+        else {
+            return Err(Error::from_string(format!("Not deleting the password for {}", url)));
+        }
+        Ok(())
+    }
+
+    /// Get the otpauth:// URI for a `url` and username.
+    pub fn get_otp_uri(&self, url: &str, username: &str) -> Result<String> {
+        if let Some(url) = host(url) {
+            self.backend.get_otp_uri(&url, username)
+        }
+        else {
+            Err(Error::from_string(format!("Cannot get the OTP URI for {}", url)))
+        }
+    }
+
+    /// Get the usernames for a `url`.
+    /// The last username used on this host comes first.
+    pub fn get_usernames(&self, url: &str) -> Result<Vec<String>> {
+        if let Some(url) = host(url) {
+            let mut usernames = self.backend.get_usernames(&url)?;
+            if let Some(last_username) = self.read_last_usernames()?.remove(&url) {
+                if let Some(index) = usernames.iter().position(|username| *username == last_username) {
+                    let username = usernames.remove(index);
+                    usernames.insert(0, username);
+                }
+            }
+            Ok(usernames)
+        }
+        else {
+            return Err(Error::from_string(format!("Cannot get the usernames for {}", url)));
+        }
+    }
+
+    /// Get the username and password for a `url` and username.
+    pub fn get(&self, url: &str, username: &str) -> Result<(String, String)> {
+        if let Some(url) = host(url) {
+            self.backend.get(&url, username)
+        }
+        else {
+            return Err(Error::from_string(format!("Cannot get the password for {}", url)));
+        }
+    }
+
+    /// Check if the credentials can be used without waiting for the backend to be unlocked.
+    pub fn is_unlocked(&self) -> bool {
+        self.backend.is_unlocked()
+    }
+
+    /// Read the last username used for each host.
+    fn read_last_usernames(&self) -> Result<HashMap<String, String>> {
+        let mut usernames = HashMap::new();
+        if let Some(ref path) = self.last_usernames_path {
+            if path.exists() {
+                let mut file = file::open(path)?;
+                let mut content = String::new();
+                file.read_to_string(&mut content)?;
+                for line in content.lines() {
+                    let mut words = line.splitn(2, ' ');
+                    if let (Some(host), Some(username)) = (words.next(), words.next()) {
+                        usernames.insert(host.to_string(), username.to_string());
+                    }
+                }
+            }
+        }
+        Ok(usernames)
+    }
+
+    /// Remember that `username` was the last one used for `url`.
+    pub fn set_last_username(&self, url: &str, username: &str) -> Result<()> {
+        if let (Some(host), Some(path)) = (host(url), self.last_usernames_path.as_ref()) {
+            let mut usernames = self.read_last_usernames()?;
+            usernames.insert(host, username.to_string());
+            let mut file = File::create(path)?;
+            for (host, username) in usernames {
+                writeln!(file, "{} {}", host, username)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Change the storage of the credentials.
+    pub fn set_backend(&mut self, backend: Box<dyn PasswordBackend>) {
+        self.backend = backend;
+    }
+
+    /// Unlock the backend in the background and send the result to `sender`.
+    pub fn unlock(&self, sender: Sender<Result<()>>) -> Result<()> {
+        self.backend.unlock(sender)
+    }
+}
+
+/// Send the `result` of an operation of a backend to the application.
+fn send_result<T>(sender: &Sender<Result<T>>, result: Result<T>) -> Result<()> {
+    sender.send(result)
+        .map_err(|error| Error::from_string(format!("Cannot send the result of the password backend: {}", error)))
+}
@@ -0,0 +1,123 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Password backend using `pass`, the standard unix password manager.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use password_store::PasswordStore;
+use relm::Sender;
+
+use app::APP_NAME;
+use errors::{Error, Result};
+use super::{PasswordBackend, send_result};
+
+/// Backend storing the credentials in `pass`, under `titanium/<host>/<username>`.
+pub struct PassBackend;
+
+impl PasswordBackend for PassBackend {
+    fn delete(&self, host: &str, username: &str, sender: Sender<Result<()>>) -> Result<()> {
+        send_result(&sender, remove(host, username))
+    }
+
+    fn get(&self, host: &str, username: &str) -> Result<(String, String)> {
+        Ok(PasswordStore::get(&path_username(host, username))?)
+    }
+
+    fn get_otp_uri(&self, host: &str, username: &str) -> Result<String> {
+        // The OTP URI is in the other lines of the entry, which are not returned by PasswordStore.
+        let content = show(host, username)?;
+        content.lines()
+            .map(str::trim)
+            .find(|line| line.starts_with("otpauth://"))
+            .map(ToString::to_string)
+            .ok_or_else(|| Error::from_string(format!("No OTP URI in the pass entry of {} for {}", username, host)))
+    }
+
+    fn get_usernames(&self, host: &str) -> Result<Vec<String>> {
+        Ok(PasswordStore::get_usernames(&path(host))?)
+    }
+
+    fn insert(&self, host: &str, username: &str, password: &str, sender: Sender<Result<bool>>) -> Result<()> {
+        send_result(&sender, insert(host, username, password))
+    }
+}
+
+/// Save the password of `username` for `host`.
+/// Return true if a password was replaced.
+fn insert(host: &str, username: &str, password: &str) -> Result<bool> {
+    // The host directory does not exist when there are no credentials for it.
+    let exists = PasswordStore::get_usernames(&path(host)).unwrap_or_default().iter()
+        .any(|existing_username| existing_username == username);
+    if !exists {
+        PasswordStore::insert(&path_username(host, username), password)?;
+        return Ok(false);
+    }
+    // Only replace the first line of the entry to keep the other ones, like the OTP URI.
+    let content = show(host, username)?;
+    let mut new_content = format!("{}\n", password);
+    for line in content.lines().skip(1) {
+        new_content.push_str(line);
+        new_content.push('\n');
+    }
+    let mut child = Command::new("pass")
+        .arg("insert")
+        .arg("--force")
+        .arg("--multiline")
+        .arg(path_username(host, username))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(new_content.as_bytes())?;
+    }
+    if !child.wait()?.success() {
+        return Err(Error::from_string(format!("Cannot update the pass entry of {} for {}", username, host)));
+    }
+    Ok(true)
+}
+
+fn path(host: &str) -> String {
+    format!("{}/{}", APP_NAME, host)
+}
+
+fn path_username(host: &str, username: &str) -> String {
+    format!("{}/{}/{}", APP_NAME, host, username)
+}
+
+/// Delete the credentials of `username` for `host`.
+fn remove(host: &str, username: &str) -> Result<()> {
+    PasswordStore::remove(&path_username(host, username))?;
+    Ok(())
+}
+
+/// Get the whole content of the entry of `username` for `host`.
+fn show(host: &str, username: &str) -> Result<String> {
+    let output = Command::new("pass")
+        .arg("show")
+        .arg(path_username(host, username))
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::from_string(format!("Cannot read the pass entry of {} for {}", username, host)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
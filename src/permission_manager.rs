@@ -26,6 +26,7 @@ use std::fmt::{self, Display, Formatter};
 use std::path::PathBuf;
 
 use glib::{Cast, ObjectExt};
+use titanium_common::urls::get_base_url;
 use webkit2gtk::{
     self,
     GeolocationPermissionRequest,
@@ -38,7 +39,6 @@ use app::App;
 use config_dir::ConfigDir;
 use errors::Result;
 use file;
-use urls::get_base_url;
 
 use self::PermissionDescription::*;
 
@@ -24,11 +24,12 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::path::PathBuf;
 
+use titanium_common::urls::get_base_url;
+
 use app::App;
 use config_dir::ConfigDir;
 use errors::Result;
 use file;
-use urls::get_base_url;
 
 /// Manager to know whether a popup should be always or never opened.
 pub struct PopupManager {
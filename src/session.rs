@@ -0,0 +1,110 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Named sessions saving the state of the windows.
+
+use std::fs::{self, read_dir, read_to_string, remove_file};
+use std::path::PathBuf;
+
+use rustc_serialize::json;
+
+use config_dir::ConfigDir;
+use errors::{Error, Result};
+
+/// The name of the session saved automatically when the `session-autosave` setting is enabled.
+pub const AUTOSAVE_SESSION: &str = "autosave";
+
+const SESSION_DIR: &str = "sessions";
+
+/// An entry of the back/forward list of a window.
+#[derive(Clone, Debug, RustcDecodable, RustcEncodable)]
+pub struct HistoryItem {
+    pub title: String,
+    pub url: String,
+}
+
+/// The state of a window in a session.
+#[derive(Clone, Debug, RustcDecodable, RustcEncodable)]
+pub struct WindowSession {
+    /// The back/forward list, for the user: the WebKit state is used to restore it.
+    pub history: Vec<HistoryItem>,
+    pub scroll_percentage: Option<i64>,
+    /// The WebKit session state (containing the back/forward list), encoded in base64.
+    pub state: Option<String>,
+    pub title: String,
+    pub url: String,
+    pub zoom_level: f64,
+}
+
+/// A session contains the state of all the windows.
+#[derive(Debug, RustcDecodable, RustcEncodable)]
+pub struct Session {
+    pub windows: Vec<WindowSession>,
+}
+
+/// Delete the session `name`.
+pub fn delete(config_dir: &ConfigDir, name: &str) -> Result<()> {
+    remove_file(session_path(config_dir, name)?)
+        .map_err(|_| Error::from_string(format!("Session {} does not exist", name)))
+}
+
+/// Get the names of the saved sessions.
+pub fn list(config_dir: &ConfigDir) -> Vec<String> {
+    let mut names = vec![];
+    if let Ok(entries) = read_dir(config_dir.data_home().join(SESSION_DIR)) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().map(|extension| extension == "json").unwrap_or(false) {
+                if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+    }
+    names.sort();
+    names
+}
+
+/// Load the session `name`.
+pub fn load(config_dir: &ConfigDir, name: &str) -> Result<Session> {
+    let content = read_to_string(session_path(config_dir, name)?)
+        .map_err(|_| Error::from_string(format!("Session {} does not exist", name)))?;
+    Ok(json::decode(&content)?)
+}
+
+/// Save the `session` under `name`, replacing the previous session with this name.
+pub fn save(config_dir: &ConfigDir, name: &str, session: &Session) -> Result<()> {
+    // Write to a temporary file first, so that a crash while saving (the autosave session is saved
+    // regularly) does not leave a truncated session.
+    let path = session_path(config_dir, name)?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, json::as_pretty_json(session).to_string())?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+fn session_path(config_dir: &ConfigDir, name: &str) -> Result<PathBuf> {
+    let name = name.trim();
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        return Err(Error::from_string(format!("Invalid session name: {}", name)));
+    }
+    Ok(config_dir.data_file(&format!("{}/{}.json", SESSION_DIR, name))?)
+}
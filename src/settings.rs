@@ -19,6 +19,7 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
+use titanium_common::HintLabels;
 use webkit2gtk;
 
 #[derive(Clone, Setting)]
@@ -40,11 +41,43 @@ impl CookieAcceptPolicy {
     }
 }
 
+#[derive(Clone, Setting)]
+pub enum HintMode {
+    #[default]
+    Letters,
+    Numbers,
+    Words,
+}
+
+impl HintMode {
+    /// Convert the setting type to the type sent to the web process.
+    pub fn to_hint_labels(&self) -> HintLabels {
+        match *self {
+            HintMode::Letters => HintLabels::Letters,
+            HintMode::Numbers => HintLabels::Numbers,
+            HintMode::Words => HintLabels::Words,
+        }
+    }
+}
+
+#[derive(Clone, Setting)]
+pub enum PasswordBackendKind {
+    Kdbx,
+    #[default]
+    Pass,
+}
+
 #[derive(Default, Settings)]
 pub struct AppSettings {
     pub cookie_accept: CookieAcceptPolicy,
     pub hint_chars: String,
+    pub hint_mode: HintMode,
     pub home_page: String,
+    pub password_backend: PasswordBackendKind,
+    pub password_kdbx_file: String,
+    pub password_kdbx_password_command: String,
+    pub password_kdbx_write: bool,
+    pub session_autosave: bool,
     pub webkit_allow_file_access_from_file_urls: bool,
     pub webkit_allow_modal_dialogs: bool,
     pub webkit_auto_load_images: bool,
@@ -0,0 +1,216 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Time-based one-time passwords (RFC 6238) generated from the otpauth:// URIs.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use hmac::digest::KeyInit;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use url::Url;
+
+use errors::{Error, Result};
+use self::Algorithm::*;
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// A one-time password generator.
+#[derive(Debug, PartialEq)]
+pub struct Totp {
+    algorithm: Algorithm,
+    digits: u32,
+    period: u64,
+    secret: Vec<u8>,
+}
+
+impl Totp {
+    /// Parse an otpauth://totp/ URI.
+    pub fn from_uri(uri: &str) -> Result<Self> {
+        let url = Url::parse(uri.trim())
+            .map_err(|_| Error::new("Invalid OTP URI"))?;
+        if url.scheme() != "otpauth" || url.host_str() != Some("totp") {
+            return Err(Error::new("Only the otpauth://totp/ URIs are supported"));
+        }
+        let mut totp = Totp {
+            algorithm: Sha1,
+            digits: 6,
+            period: 30,
+            secret: vec![],
+        };
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "algorithm" =>
+                    totp.algorithm =
+                        match value.to_uppercase().as_str() {
+                            "SHA1" => Sha1,
+                            "SHA256" => Sha256,
+                            "SHA512" => Sha512,
+                            _ => return Err(Error::from_string(format!("Unsupported OTP algorithm {}", value))),
+                        },
+                "digits" =>
+                    totp.digits = value.parse()
+                        .map_err(|_| Error::new("Invalid number of digits in the OTP URI"))?,
+                "period" =>
+                    totp.period = value.parse()
+                        .map_err(|_| Error::new("Invalid period in the OTP URI"))?,
+                "secret" => totp.secret = base32_decode(&value)?,
+                _ => (),
+            }
+        }
+        if totp.secret.is_empty() {
+            return Err(Error::new("No secret in the OTP URI"));
+        }
+        // More digits would overflow the code.
+        if totp.digits == 0 || totp.digits > 9 {
+            return Err(Error::new("Invalid number of digits in the OTP URI"));
+        }
+        if totp.period == 0 {
+            return Err(Error::new("Invalid period in the OTP URI"));
+        }
+        Ok(totp)
+    }
+
+    /// Generate the code for the current time.
+    pub fn generate(&self) -> String {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        self.generate_at(time)
+    }
+
+    /// Generate the code for `time`, in seconds since the Unix epoch.
+    fn generate_at(&self, time: u64) -> String {
+        let counter = (time / self.period).to_be_bytes();
+        let hash =
+            match self.algorithm {
+                Sha1 => sign::<Hmac<Sha1>>(&self.secret, &counter),
+                Sha256 => sign::<Hmac<Sha256>>(&self.secret, &counter),
+                Sha512 => sign::<Hmac<Sha512>>(&self.secret, &counter),
+            };
+        // Dynamic truncation (RFC 4226, section 5.3).
+        let offset = (hash[hash.len() - 1] & 0xF) as usize;
+        let code = (u32::from(hash[offset]) & 0x7F) << 24 |
+            u32::from(hash[offset + 1]) << 16 |
+            u32::from(hash[offset + 2]) << 8 |
+            u32::from(hash[offset + 3]);
+        format!("{:0width$}", code % 10_u32.pow(self.digits), width = self.digits as usize)
+    }
+}
+
+/// Decode a base32 string (RFC 4648), ignoring the case, the padding and the spaces.
+fn base32_decode(input: &str) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut buffer = 0_u32;
+    let mut bits = 0;
+    for character in input.chars().filter(|&character| character != '=' && !character.is_whitespace()) {
+        let character = character.to_ascii_uppercase() as u8;
+        let value = BASE32_ALPHABET.iter().position(|&letter| letter == character)
+            .ok_or_else(|| Error::new("Invalid character in the OTP secret"))?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(bytes)
+}
+
+fn sign<M: Mac + KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as KeyInit>::new_from_slice(key)
+        .expect("HMAC accepts keys of any size");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Totp, base32_decode};
+    use super::Algorithm::{self, Sha1, Sha256, Sha512};
+
+    fn totp(algorithm: Algorithm, secret: &[u8]) -> Totp {
+        Totp {
+            algorithm,
+            digits: 8,
+            period: 30,
+            secret: secret.to_vec(),
+        }
+    }
+
+    #[test]
+    fn decode_base32() {
+        assert_eq!(base32_decode("JBSWY3DPEHPK3PXP").ok(), Some(b"Hello!\xDE\xAD\xBE\xEF".to_vec()));
+        assert_eq!(base32_decode("jbsw y3dp ehpk 3pxp").ok(), Some(b"Hello!\xDE\xAD\xBE\xEF".to_vec()));
+        assert_eq!(base32_decode("MZXW6===").ok(), Some(b"foo".to_vec()));
+        assert!(base32_decode("MZXW1").is_err());
+    }
+
+    #[test]
+    fn parse_uri() {
+        let totp = Totp::from_uri("otpauth://totp/Example:alice@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example");
+        assert_eq!(totp.ok(), Some(Totp {
+            algorithm: Sha1,
+            digits: 6,
+            period: 30,
+            secret: b"Hello!\xDE\xAD\xBE\xEF".to_vec(),
+        }));
+        let totp = Totp::from_uri("otpauth://totp/Example?secret=MZXW6===&algorithm=SHA256&digits=8&period=60");
+        assert_eq!(totp.ok(), Some(Totp {
+            algorithm: Sha256,
+            digits: 8,
+            period: 60,
+            secret: b"foo".to_vec(),
+        }));
+        assert!(Totp::from_uri("otpauth://hotp/Example?secret=JBSWY3DPEHPK3PXP").is_err());
+        assert!(Totp::from_uri("otpauth://totp/Example").is_err());
+        assert!(Totp::from_uri("otpauth://totp/Example?secret=JBSWY3DPEHPK3PXP&algorithm=MD5").is_err());
+    }
+
+    #[test]
+    fn rfc6238_test_vectors() {
+        let sha1 = totp(Sha1, b"12345678901234567890");
+        let sha256 = totp(Sha256, b"12345678901234567890123456789012");
+        let sha512 = totp(Sha512, b"1234567890123456789012345678901234567890123456789012345678901234");
+        let vectors = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for &(time, sha1_code, sha256_code, sha512_code) in &vectors {
+            assert_eq!(sha1.generate_at(time), sha1_code);
+            assert_eq!(sha256.generate_at(time), sha256_code);
+            assert_eq!(sha512.generate_at(time), sha512_code);
+        }
+    }
+}
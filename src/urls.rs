@@ -38,20 +38,6 @@ pub fn canonicalize_url(url: &str) -> String {
     url.to_string()
 }
 
-/// Get the base URL (domain and tld) of an URL.
-pub fn get_base_url(url: &str) -> Option<String> {
-    Url::parse(url).ok().map(|parsed_url| {
-        let mut base_url = parsed_url.host_str().unwrap_or("").to_string();
-        // Remove all sub-domains.
-        let mut period_count = base_url.chars().filter(|&c| c == '.').count();
-        while period_count > 1 {
-            base_url = base_url.chars().skip_while(|&c| c != '.').skip(1).collect();
-            period_count = base_url.chars().filter(|&c| c == '.').count();
-        }
-        base_url
-    })
-}
-
 /// Get the filename from the URL.
 pub fn get_filename(url: &str) -> Option<String> {
     Url::parse(url).ok()
@@ -63,12 +49,6 @@ pub fn get_filename(url: &str) -> Option<String> {
         )
 }
 
-pub fn host(url: &str) -> Option<String> {
-    Url::parse(url).ok().and_then(|parsed_url| {
-        parsed_url.host_str().map(|host| host.to_string())
-    })
-}
-
 /// Check if the input string looks like a URL.
 pub fn is_url(input: &str) -> bool {
     Url::parse(input).is_ok() || (Url::parse(&format!("http://{}", input)).is_ok() &&
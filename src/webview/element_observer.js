@@ -0,0 +1,22 @@
+// Tell the web extension that elements were inserted in the page, so that it hides those matched by
+// the generic cosmetic filters of the adblocker and fills the password inputs of multi-step logins.
+// The mutations are batched to send at most one event every DELAY milliseconds.
+(function() {
+    var DELAY = 100;
+    var scheduled = false;
+
+    var observer = new MutationObserver(function() {
+        if (scheduled) {
+            return;
+        }
+        scheduled = true;
+        setTimeout(function() {
+            scheduled = false;
+            document.dispatchEvent(new Event("titanium-elements-inserted"));
+        }, DELAY);
+    });
+
+    if (document.documentElement) {
+        observer.observe(document.documentElement, { childList: true, subtree: true });
+    }
+})();
@@ -71,7 +71,7 @@ use webkit2gtk::{
 use webkit2gtk::NavigationType::{LinkClicked, Other};
 use webkit2gtk::PolicyDecisionType::{self, NavigationAction, Response};
 use webkit2gtk::ProcessModel::MultipleSecondaryProcesses;
-use webkit2gtk::UserContentInjectedFrames::AllFrames;
+use webkit2gtk::UserContentInjectedFrames::{AllFrames, TopFrame};
 use webkit2gtk::UserScriptInjectionTime::End;
 use webkit2gtk::UserStyleLevel::User;
 
@@ -82,6 +82,12 @@ use self::Msg::*;
 use settings::AppSettingsVariant;
 use stylesheet::get_stylesheet_and_whitelist;
 
+/// The script notifying the web extension of the elements inserted in the page.
+const ELEMENT_OBSERVER_SCRIPT: &str = include_str!("element_observer.js");
+
+/// The script world in which the scripts of the browser run, isolated from the scripts of the page.
+const SCRIPT_WORLD: &str = "titanium";
+
 pub struct Model {
     clicked_url: Rc<RefCell<Option<String>>>,
     config_dir: ConfigDir,
@@ -94,6 +100,7 @@ pub struct Model {
 
 #[derive(Msg)]
 pub enum Msg {
+    AddCosmeticStylesheet(String),
     AddScripts,
     AddStylesheets,
     AppError(String),
@@ -147,6 +154,7 @@ impl Widget for WebView {
 
     fn update(&mut self, event: Msg) {
         match event {
+            AddCosmeticStylesheet(css) => self.add_cosmetic_stylesheet(&css),
             AddScripts => handle_app_error!(self.add_scripts()),
             AddStylesheets => handle_app_error!(self.add_stylesheets()),
             AppError(_) => (), // To be listened by the user.
@@ -201,10 +209,21 @@ impl Widget for WebView {
 }
 
 impl WebView {
+    /// Add a user stylesheet hiding the elements matched by the cosmetic filters of the adblocker.
+    /// It is removed with the other stylesheets when the next page starts loading.
+    fn add_cosmetic_stylesheet(&self, css: &str) {
+        if let Some(content_manager) = self.widgets.view.user_content_manager() {
+            let stylesheet = UserStyleSheet::new(css, TopFrame, User, &[], &[]);
+            content_manager.add_style_sheet(&stylesheet);
+        }
+    }
+
     /// Add the user scripts.
     fn add_scripts(&self) -> Result<()> {
         if let Some(content_manager) = self.widgets.view.user_content_manager() {
             content_manager.remove_all_scripts();
+            let script = UserScript::for_world(ELEMENT_OBSERVER_SCRIPT, TopFrame, End, SCRIPT_WORLD, &[], &[]);
+            content_manager.add_script(&script);
             let script_path = self.model.config_dir.config_file("scripts")?;
             for filename in read_dir(script_path)? {
                 let mut file = file::open(filename?.path())?;
@@ -23,7 +23,13 @@ use settings::{AppSettingsVariant, CookieAcceptPolicy};
 use settings::AppSettingsVariant::{
     CookieAccept,
     HintChars,
+    HintMode,
     HomePage,
+    PasswordBackend,
+    PasswordKdbxFile,
+    PasswordKdbxPasswordCommand,
+    PasswordKdbxWrite,
+    SessionAutosave,
     WebkitAllowFileAccessFromFileUrls,
     WebkitAllowModalDialogs,
     WebkitAutoLoadImages,
@@ -97,7 +103,8 @@ impl WebView {
         if let Some(settings) = self.widgets.view.settings() {
             match setting {
                 CookieAccept(ref value) => self.set_cookie_accept(value),
-                HintChars(_) | HomePage(_) => (),
+                HintChars(_) | HintMode(_) | HomePage(_) | PasswordBackend(_) | PasswordKdbxFile(_) |
+                    PasswordKdbxPasswordCommand(_) | PasswordKdbxWrite(_) | SessionAutosave(_) => (),
                 WebkitAllowFileAccessFromFileUrls(value) =>
                     settings.set_allow_file_access_from_file_urls(value),
                 WebkitAllowModalDialogs(value) =>
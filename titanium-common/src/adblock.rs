@@ -0,0 +1,284 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Location of the adblock filter lists, shared by the UI process, which compiles them, and the
+//! web processes, which use them.
+//! The whitelist of the sites on which the adblocker is disabled is shared in the same way.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use urls::host;
+
+/// The directory, in the config directory, containing the filter lists written by the user.
+pub const USER_LISTS_DIR: &str = "adblocklists";
+
+/// The directory, in the data directory, containing the subscribed filter lists.
+pub const SUBSCRIPTIONS_DIR: &str = "adblock";
+
+/// The file, in the data directory, containing the serialized adblock engine.
+pub const ENGINE_CACHE_FILE: &str = "adblock-engine.dat";
+
+/// The file, in the config directory, containing the hosts on which the adblocker is disabled.
+pub const WHITELIST_FILE: &str = "adblock/whitelist";
+
+/// The hosts on which the adblocker is disabled.
+/// The adblocker is also disabled on the subdomains of these hosts.
+pub struct Whitelist {
+    hosts: HashSet<String>,
+    path: PathBuf,
+}
+
+impl Whitelist {
+    /// Create an empty whitelist saved in the file at `path`.
+    pub fn new(path: PathBuf) -> Self {
+        Whitelist {
+            hosts: HashSet::new(),
+            path,
+        }
+    }
+
+    /// Add the host of the url to the whitelist.
+    /// Returns the host that was added, if any.
+    pub fn add(&mut self, url: &str) -> io::Result<Option<String>> {
+        // Reload the file since it might have been modified by another window.
+        self.load()?;
+        match host(url) {
+            Some(host) if !host.is_empty() => {
+                self.hosts.insert(host.clone());
+                self.save()?;
+                Ok(Some(host))
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Check if the adblocker is disabled on the host of the url or on one of its parent domains.
+    pub fn contains(&self, url: &str) -> bool {
+        let host =
+            match host(url) {
+                Some(host) => host,
+                None => return false,
+            };
+        let mut domain = host.as_str();
+        loop {
+            if self.hosts.contains(domain) {
+                return true;
+            }
+            match domain.find('.') {
+                Some(index) => domain = &domain[index + 1..],
+                None => return false,
+            }
+        }
+    }
+
+    /// Load the hosts from the file.
+    /// The whitelist is empty when the file does not exist.
+    pub fn load(&mut self) -> io::Result<()> {
+        let file =
+            match File::open(&self.path) {
+                Ok(file) => file,
+                Err(ref error) if error.kind() == ErrorKind::NotFound => {
+                    self.hosts.clear();
+                    return Ok(());
+                },
+                Err(error) => return Err(error),
+            };
+        let lines: io::Result<Vec<_>> = BufReader::new(file).lines().collect();
+        self.hosts = lines?.into_iter()
+            .filter(|line| !line.is_empty())
+            .collect();
+        Ok(())
+    }
+
+    /// Remove the host of the url from the whitelist.
+    /// Returns the host that was removed, if any.
+    pub fn remove(&mut self, url: &str) -> io::Result<Option<String>> {
+        self.load()?;
+        if let Some(host) = host(url) {
+            if self.hosts.remove(&host) {
+                self.save()?;
+                return Ok(Some(host));
+            }
+        }
+        Ok(None)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let mut file = File::create(&self.path)?;
+        for host in &self.hosts {
+            writeln!(file, "{}", host)?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the paths of the filter lists: all the files in `user_lists_dir` and the `.txt` files in
+/// `subscriptions_dir`.
+pub fn list_paths(user_lists_dir: &Path, subscriptions_dir: &Path) -> Vec<PathBuf> {
+    let mut paths = files_in(user_lists_dir);
+    paths.extend(files_in(subscriptions_dir).into_iter()
+        .filter(|path| path.extension().map(|ext| ext == "txt").unwrap_or(false)));
+    paths
+}
+
+/// Create the content of the engine cache: the paths of the filter lists it was compiled from, to
+/// detect when one of them is removed, followed by the serialized engine.
+pub fn engine_cache_content(list_paths: &[PathBuf], serialized_engine: &[u8]) -> Vec<u8> {
+    let mut content = vec![];
+    for path in sorted_paths(list_paths) {
+        content.extend_from_slice(path.as_bytes());
+        content.push(b'\n');
+    }
+    content.push(b'\n');
+    content.extend_from_slice(serialized_engine);
+    content
+}
+
+/// Check if the engine cache is missing, older than one of the filter lists or compiled from other
+/// filter lists.
+pub fn is_cache_stale(cache_path: &Path, list_paths: &[PathBuf]) -> bool {
+    open_cache(cache_path, list_paths).is_none()
+}
+
+/// Read the serialized engine from the cache, unless it is stale.
+pub fn read_engine_cache(cache_path: &Path, list_paths: &[PathBuf]) -> Option<Vec<u8>> {
+    let mut reader = open_cache(cache_path, list_paths)?;
+    let mut serialized_engine = vec![];
+    reader.read_to_end(&mut serialized_engine).ok()?;
+    Some(serialized_engine)
+}
+
+/// Read the rules of the filter lists.
+pub fn load_rules(list_paths: &[PathBuf]) -> Vec<String> {
+    let mut rules = vec![];
+    for path in list_paths {
+        if let Ok(file) = File::open(path) {
+            let file = BufReader::new(file);
+            let lines: io::Result<Vec<_>> = file.lines().collect();
+            if let Ok(lines) = lines {
+                rules.extend(lines);
+            }
+        }
+    }
+    rules
+}
+
+fn files_in(directory: &Path) -> Vec<PathBuf> {
+    fs::read_dir(directory)
+        .map(|entries| entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect())
+        .unwrap_or_default()
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// Open the engine cache and read the paths of the filter lists at its start.
+// Returns the reader positioned at the start of the serialized engine if the cache is not stale.
+fn open_cache(cache_path: &Path, list_paths: &[PathBuf]) -> Option<BufReader<File>> {
+    let cache_time = modified_time(cache_path)?;
+    let is_list_newer = list_paths.iter()
+        .any(|path| modified_time(path).map(|time| time > cache_time).unwrap_or(true));
+    if is_list_newer {
+        return None;
+    }
+
+    let mut reader = BufReader::new(File::open(cache_path).ok()?);
+    let mut cached_paths = vec![];
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            // The end of the paths was not found: the cache is invalid.
+            return None;
+        }
+        let path = line.trim_end_matches('\n');
+        if path.is_empty() {
+            break;
+        }
+        cached_paths.push(path.to_string());
+    }
+
+    if cached_paths == sorted_paths(list_paths) {
+        Some(reader)
+    }
+    else {
+        None
+    }
+}
+
+fn sorted_paths(list_paths: &[PathBuf]) -> Vec<String> {
+    let mut paths: Vec<_> = list_paths.iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{Whitelist, engine_cache_content, is_cache_stale, read_engine_cache};
+
+    #[test]
+    fn test_engine_cache() {
+        let directory = env::temp_dir().join(format!("titanium-adblock-test-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let list1 = directory.join("list1.txt");
+        let list2 = directory.join("list2.txt");
+        fs::write(&list1, "||ads.example.com^").unwrap();
+        fs::write(&list2, "##.ad").unwrap();
+        let cache_path = directory.join("cache");
+        let list_paths = vec![list2.clone(), list1.clone()];
+        fs::write(&cache_path, engine_cache_content(&list_paths, b"engine\n\ndata")).unwrap();
+
+        assert_eq!(read_engine_cache(&cache_path, &[list1.clone(), list2.clone()]), Some(b"engine\n\ndata".to_vec()));
+        // A removed list makes the cache stale.
+        assert!(is_cache_stale(&cache_path, &list_paths[1..]));
+        // So does a new list.
+        assert!(is_cache_stale(&cache_path, &[list1.clone(), list2.clone(), directory.join("list3.txt")]));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_whitelist_contains() {
+        let mut whitelist = Whitelist::new(PathBuf::new());
+        whitelist.hosts.insert("bbc.co.uk".to_string());
+        assert!(whitelist.contains("https://bbc.co.uk/news"));
+        assert!(whitelist.contains("https://www.bbc.co.uk/"));
+        assert!(!whitelist.contains("https://example.co.uk/"));
+        assert!(!whitelist.contains("https://notbbc.co.uk/"));
+        assert!(!whitelist.contains("about:blank"));
+    }
+}
@@ -33,8 +33,15 @@
 
 extern crate rmp_serialize;
 extern crate rustc_serialize;
+extern crate url;
 
+pub mod adblock;
 pub mod protocol;
+pub mod urls;
+
+/// The event sent to the document by the element observer script of the UI process when elements
+/// are inserted in the page.
+pub const ELEMENTS_INSERTED_EVENT: &str = "titanium-elements-inserted";
 
 /// The mark that goes to the last position after a jump.
 pub const LAST_MARK: u8 = b'\'';
@@ -44,6 +51,8 @@ pub const LAST_MARK: u8 = b'\'';
 pub enum Action {
     /// Copy the specified link in the clipboard.
     CopyLink(String),
+    /// Copy the specified text in the clipboard.
+    CopyText(String),
     /// Download the specified destination.
     DownloadLink(String),
     /// Show the file input.
@@ -52,20 +61,57 @@ pub enum Action {
     GoInInsertMode,
     /// No action.
     NoAction,
+    /// Open the specified link in a new window in the background.
+    OpenInBackgroundWindow(String),
+    /// Open the specified link in a new private window.
+    OpenInPrivateWindow(String),
+    /// Open the specified URL in the current window.
+    OpenUrl(String),
 }
 
 /// The mode for the follow mode.
 /// This indicates the action that will be taken after an hint is selected.
-#[derive(Clone, Copy, Debug, RustcEncodable, RustcDecodable)]
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
 pub enum FollowMode {
+    /// The page will go to the anchor of the element (the hints are shown on the headings and on
+    /// the elements with an id or a name).
+    Anchor,
     /// The link will be clicked.
     Click,
+    /// The URL of the anchor of the element will be copied.
+    CopyAnchor,
     /// The URL of the link will be copied.
     CopyLink,
+    /// The text of the element will be copied.
+    CopyText,
     /// The source of the link will be downloaded.
     Download,
+    /// The source of the image will be downloaded.
+    DownloadImage,
+    /// The element will be focused without being clicked.
+    Focus,
     /// The cursor will move over the link.
     Hover,
+    /// The link will be opened in a new private window.
+    PrivateWindow,
+    /// The link will be opened in a new window in the background and the hints will stay shown
+    /// to select other links.
+    Rapid,
+    /// The page will be scrolled to show the element.
+    ScrollIntoView,
+    /// The text of the element will be selected.
+    Select,
+}
+
+/// The kind of labels shown on the hints.
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum HintLabels {
+    /// Labels made of the hint characters.
+    Letters,
+    /// Numeric labels: the letters are used to filter the hints by their text.
+    Numbers,
+    /// Short dictionary words.
+    Words,
 }
 
 /// The decoder fails with "variant type overflow" when directly using InnerMessage, so wrap it in
@@ -83,6 +129,14 @@ pub enum InnerMessage {
     ActivateHint(FollowMode, bool),
     /// Click on the link in the selection.
     ActivateSelection(),
+    /// Add a user stylesheet, containing the CSS of the cosmetic filters, to the page at the url.
+    AddCosmeticStylesheet(String, String),
+    /// Save the current position in the jump list.
+    AddJump(),
+    /// Do not block the next request to the specified url.
+    /// Only the web process receiving this message allows the request: if the navigation to the
+    /// url is done in another web process (e.g. WebKit swapped the process), it is blocked again.
+    AllowRequestOnce(String),
     /// Response to EnterHintKey.
     /// Contains the link (href) if it is a anchor element.
     ClickHintElement(Option<String>),
@@ -104,8 +158,16 @@ pub enum InnerMessage {
     GoToMark(u8),
     /// Hide the hints.
     HideHints(),
+    /// Tell that a password or credit card input is on a page not using HTTPS.
+    InsecureForm(),
     /// Insert some text in the currently focused text field.
     InsertText(String),
+    /// Go to the previous position in the jump list.
+    JumpBackward(),
+    /// Go to the next position in the jump list.
+    JumpForward(),
+    /// Write the generated password in the password inputs of the form and send the credentials.
+    LoadGeneratedPassword(String),
     /// Write the username and password in the login form.
     LoadUsernamePass(String, String),
     /// Add a new mark at the current position.
@@ -114,6 +176,15 @@ pub enum InnerMessage {
     /// This is used when starting a new titanium process to tell the existing process to open a
     /// new window.
     Open(Vec<String>),
+    /// Reload the filter lists of the adblocker.
+    ReloadAdblockLists(),
+    /// Reload the list of sites on which the adblocker is disabled.
+    ReloadAdblockWhitelist(),
+    /// Remove the last character typed in follow mode.
+    RemoveHintKey(),
+    /// Tell the UI that the request to the url was blocked by the adblock rule.
+    /// The boolean is true when the request is for the main document.
+    RequestBlocked(String, String, bool),
     /// Reset the marks.
     ResetMarks(),
     /// Set the scrolling element.
@@ -130,10 +201,13 @@ pub enum InnerMessage {
     ScrollToPercent(u32),
     /// Set the selected file on a file input.
     SelectFile(String),
-    /// Show the hints over the elements.
-    ShowHints(String),
+    /// Show the hints over the elements that can be selected in the specified follow mode.
+    /// The labels are generated from the hint characters according to the kind of labels.
+    ShowHints(String, HintLabels, FollowMode),
     /// Submit the login form.
     SubmitLoginForm(),
+    /// Switch between typing the labels of the hints and filtering the hints by their text.
+    ToggleHintText(),
 }
 
 /// Either all the page is shown (hence, no percentage) or a value between 0 and 100.
@@ -19,56 +19,143 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
-//! Host-based adblocker.
+//! Adblocker using filter lists in the Adblock Plus format.
 
-use std::fs::{File, read_dir};
-use std::io::{self, BufRead, BufReader};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::PathBuf;
 
+use adblock::cosmetic_filter_cache::UrlSpecificResources;
 use adblock::engine::Engine;
 use adblock::lists::FilterFormat;
+use titanium_common::adblock::{
+    ENGINE_CACHE_FILE,
+    SUBSCRIPTIONS_DIR,
+    USER_LISTS_DIR,
+    WHITELIST_FILE,
+    Whitelist,
+    list_paths,
+    load_rules,
+    read_engine_cache,
+};
+use url::Url;
 use xdg::BaseDirectories;
 
 use super::APP_NAME;
 
+thread_local! {
+    pub static ADBLOCKER: Adblocker = Adblocker::new();
+}
+
 pub struct Adblocker {
-    engine: Engine,
+    /// The urls that must not be blocked the next time they are requested.
+    allowed_once: RefCell<HashSet<String>>,
+    engine: RefCell<Engine>,
+    /// The sites on which the adblocker is disabled.
+    whitelist: RefCell<Whitelist>,
 }
 
 impl Adblocker {
     /// Create a new adblocker.
     pub fn new() -> Self {
         Adblocker {
-            engine: Engine::from_rules(&Adblocker::load_lists_urls(), FilterFormat::Standard),
+            allowed_once: RefCell::new(HashSet::new()),
+            engine: RefCell::new(Adblocker::create_engine()),
+            whitelist: RefCell::new(Adblocker::read_whitelist()),
+        }
+    }
+
+    /// Do not block the next request to `url`.
+    pub fn allow_once(&self, url: &str) {
+        self.allowed_once.borrow_mut().insert(url.to_string());
+    }
+
+    /// Check if the request to `url` made by the page at `source_url` should be blocked.
+    /// Returns the rule blocking the request, if any.
+    pub fn blocking_rule(&self, url: &str, source_url: &str, request_type: &str) -> Option<String> {
+        if self.whitelist.borrow().contains(source_url) || self.allowed_once.borrow_mut().remove(url) {
+            return None;
+        }
+        let blocker_result = self.engine.borrow().check_network_urls(url, source_url, request_type);
+        // An exception rule (e.g. @@||github.com/adgear/^$document) overrides the blocking rule.
+        if blocker_result.matched && blocker_result.exception.is_none() {
+            Some(blocker_result.filter.unwrap_or_else(|| "unknown rule".to_string()))
+        }
+        else {
+            None
         }
     }
 
-    /// Get the blacklisted urls.
-    fn load_lists_urls() -> Vec<String> {
-        let mut result = vec![];
-        let xdg_dirs = unwrap_or_ret!(BaseDirectories::with_prefix(APP_NAME), result);
-        let lists_path = unwrap_or_ret!(xdg_dirs.place_config_file("adblocklists"), result);
-
-        for entry in unwrap_or_ret!(read_dir(&lists_path), result) {
-            if let Ok(entry) = entry {
-                let list = lists_path.join(entry.path());
-                if let Ok(file) = File::open(&list) {
-                    let file = BufReader::new(file);
-                    let urls: io::Result<Vec<_>> = file.lines().collect();
-                    if let Ok(urls) = urls {
-                        result.extend(urls);
-                    }
-                }
+    /// Create the engine from the cache compiled by the UI process, or from the rules of the filter
+    /// lists if the cache is missing or stale.
+    fn create_engine() -> Engine {
+        let xdg_dirs = unwrap_or_ret!(BaseDirectories::with_prefix(APP_NAME), Engine::new(true));
+        let user_lists_dir = xdg_dirs.get_config_home().join(USER_LISTS_DIR);
+        let subscriptions_dir = xdg_dirs.get_data_home().join(SUBSCRIPTIONS_DIR);
+        let list_paths = list_paths(&user_lists_dir, &subscriptions_dir);
+
+        let cache_path = xdg_dirs.get_data_home().join(ENGINE_CACHE_FILE);
+        if let Some(serialized) = read_engine_cache(&cache_path, &list_paths) {
+            let mut engine = Engine::new(true);
+            match engine.deserialize(&serialized) {
+                Ok(()) => return engine,
+                Err(error) => warn!("Cannot deserialize the adblock engine cache: {:?}", error),
             }
         }
 
-        result
+        // Use the debug mode to keep the text of the rules, to show the user which one blocked a page.
+        Engine::from_rules_debug(&load_rules(&list_paths), FilterFormat::Standard)
+    }
+
+    /// Get the selectors of the generic cosmetic filters hiding the elements with the specified
+    /// `classes` and `ids`.
+    pub fn hidden_class_id_selectors(&self, classes: &[String], ids: &[String], exceptions: &HashSet<String>)
+        -> Vec<String>
+    {
+        self.engine.borrow().hidden_class_id_selectors(classes, ids, exceptions)
     }
 
-    /// Check if the specified url should be blocked.
-    pub fn should_block(&self, url: &str) -> bool {
-        let blocker_result = self.engine.check_network_urls(url, "", ""); // TODO: add missing parameters?
-        // TODO: check the exception field to make whitelist working?
-        // @@||github.com/adgear/^$document
-        blocker_result.matched
+    /// Read the sites on which the adblocker is disabled.
+    fn read_whitelist() -> Whitelist {
+        let xdg_dirs = unwrap_or_ret!(BaseDirectories::with_prefix(APP_NAME), Whitelist::new(PathBuf::new()));
+        let mut whitelist = Whitelist::new(xdg_dirs.get_config_home().join(WHITELIST_FILE));
+        if let Err(error) = whitelist.load() {
+            warn!("Cannot read the adblock whitelist: {}", error);
+        }
+        whitelist
+    }
+
+    /// Reload the filter lists.
+    pub fn reload_lists(&self) {
+        *self.engine.borrow_mut() = Adblocker::create_engine();
+    }
+
+    /// Reload the sites on which the adblocker is disabled.
+    pub fn reload_whitelist(&self) {
+        *self.whitelist.borrow_mut() = Adblocker::read_whitelist();
+    }
+
+    /// Get the cosmetic filters specific to the page at `url`.
+    pub fn url_cosmetic_resources(&self, url: &str) -> UrlSpecificResources {
+        self.engine.borrow().url_cosmetic_resources(url)
+    }
+}
+
+/// Guess the type of the request from the extension of the url, since WebKit does not give it.
+pub fn request_type(url: &str, source_url: &str) -> &'static str {
+    if url == source_url {
+        return "document";
+    }
+    let path = Url::parse(url).ok()
+        .map(|url| url.path().to_lowercase())
+        .unwrap_or_default();
+    let extension = path.rsplit('.').next().unwrap_or("");
+    match extension {
+        "js" | "mjs" => "script",
+        "css" => "stylesheet",
+        "avif" | "bmp" | "gif" | "ico" | "jpeg" | "jpg" | "png" | "svg" | "webp" => "image",
+        "eot" | "otf" | "ttf" | "woff" | "woff2" => "font",
+        "mp3" | "mp4" | "ogg" | "opus" | "wav" | "webm" => "media",
+        _ => "other",
     }
 }
@@ -207,9 +207,8 @@ pub fn get_href(element: &DOMHTMLElement) -> Option<String> {
     }
 }
 
-fn get_frame_offsets(element: &DOMElement) -> Option<Pos> {
-    let element = element.clone().upcast::<DOMNode>();
-    let document = element.owner_document()?;
+/// Get the position of the frame containing the document relative to the main window.
+pub fn get_frame_offsets(document: &DOMDocument) -> Option<Pos> {
     let mut window = document.default_view();
     let mut previous_window = None;
     let mut x = 0.0;
@@ -241,9 +240,9 @@ fn get_frame_offsets(element: &DOMElement) -> Option<Pos> {
 pub fn get_position(element: &DOMElement) -> Option<Pos> {
     let rects = element.client_rects()?;
     let rect = rects.item(0)?;
-    let frame_offsets = get_frame_offsets(element).unwrap_or(Pos { x: 0.0, y: 0.0 });
-
     let document = element.owner_document()?;
+    let frame_offsets = get_frame_offsets(&document).unwrap_or(Pos { x: 0.0, y: 0.0 });
+
     let window = document.default_view()?;
     let scroll_x = window.scroll_x();
     let scroll_y = window.scroll_y();
@@ -289,8 +288,7 @@ pub fn is_enabled(element: &DOMElement) -> bool {
 }
 
 /// Check if an element is hidden.
-/// This is not exactly the opposite as `is_visible` since `is_hidden` returns false for elements that
-/// are visible, but outside the viewport.
+/// This returns false for elements that are visible, but outside the viewport.
 pub fn is_hidden(document: &DOMDocument, element: &DOMElement) -> bool {
     let window = unwrap_opt_or_ret!(document.default_view(), true);
     let mut element = Some(element.clone());
@@ -323,20 +321,6 @@ pub fn is_text_input(element: &DOMElement) -> bool {
     }
 }
 
-/// Check if an element is visible and in the viewport.
-pub fn is_visible(document: &DOMDocument, element: &DOMElement) -> bool {
-    let window = unwrap_opt_or_ret!(document.default_view(), false);
-    let rect = unwrap_opt_or_ret!(element.bounding_client_rect(), false);
-    let x1 = rect.left();
-    let x2 = rect.right();
-    let y1 = rect.top();
-    let y2 = rect.bottom();
-
-    let height = window.inner_height() as f32;
-    let width = window.inner_width() as f32;
-    (x1 >= 0.0 || x2 >= 0.0) && x1 < width && (y1 >= 0.0 || y2 >= 0.0) && y1 < height
-}
-
 /// Trigger a mouse down event on the element.
 pub fn mouse_down(element: &DOMElement) {
     mouse_event("mousedown", element, false);
@@ -350,13 +334,23 @@ pub fn mouse_enter(element: &DOMElement) {
 
 /// Trigger a change event on the element.
 pub fn change_event(element: &DOMElement) {
+    html_event("change", element);
+}
+
+/// Trigger an HTML event which bubbles, like the native ones, on the element.
+fn html_event(event_name: &str, element: &DOMElement) {
     let event = wtry_opt_no_ret!(element.owner_document()
         .and_then(|document| document.create_event("HTMLEvents").ok()));
-    event.init_event("change", false, true);
+    event.init_event(event_name, true, true);
     let element: DOMEventTarget = element.clone().upcast();
     wtry!(element.dispatch_event(&event));
 }
 
+/// Trigger an input event on the element.
+pub fn input_event(element: &DOMElement) {
+    html_event("input", element);
+}
+
 trait DOMEventTargetExtManual {
     fn dispatch_event(&self, event: &impl IsA<DOMEvent>) -> Result<(), glib::Error>;
 }
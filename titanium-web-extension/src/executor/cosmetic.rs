@@ -0,0 +1,120 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Cosmetic filtering: hide the elements matched by the element hiding rules of the adblock lists.
+
+use webkit2gtk_webextension::{
+    traits::{
+        DOMDocumentExt,
+        DOMElementExt,
+        WebPageExt,
+    },
+    DOMElement,
+};
+
+use titanium_common::InnerMessage::AddCosmeticStylesheet;
+
+use adblocker::ADBLOCKER;
+use dom::NodeIter;
+use executor::Executor;
+use hints::HINTS_ID;
+
+const GENERIC_SELECTOR: &str = "[id],[class]";
+
+impl Executor {
+    /// Hide the elements matched by the cosmetic filters of the current page.
+    pub fn apply_cosmetic_filters(&mut self) {
+        let url = wtry_opt_no_ret!(self.model.page.uri());
+        let resources = ADBLOCKER.with(|adblocker| adblocker.url_cosmetic_resources(&url));
+        self.model.cosmetic_classes.clear();
+        self.model.cosmetic_ids.clear();
+        self.model.cosmetic_exceptions = resources.exceptions;
+        self.model.generic_hide = resources.generichide;
+
+        let mut css = hide_css(resources.hide_selectors.iter());
+        for (selector, styles) in resources.style_selectors {
+            css.push_str(&format!("{} {{ {} }}\n", selector, styles.join("; ")));
+        }
+        self.add_stylesheet(css);
+
+        self.hide_inserted_elements();
+    }
+
+    /// Hide the elements matched by a generic filter, including the elements inserted since the
+    /// last call.
+    pub fn hide_inserted_elements(&mut self) {
+        if self.model.generic_hide {
+            return;
+        }
+        let document = get_document!(self);
+        let elements = NodeIter::new(document.query_selector_all(GENERIC_SELECTOR).ok());
+        self.hide_generic_elements(elements);
+    }
+
+    // Ask the UI process to add a user stylesheet containing the specified CSS to the page.
+    // A user stylesheet, unlike a style element, cannot be blocked or removed by the page.
+    fn add_stylesheet(&self, css: String) {
+        if css.is_empty() {
+            return;
+        }
+        let url = wtry_opt_no_ret!(self.model.page.uri());
+        self.send(AddCosmeticStylesheet(url.to_string(), css));
+    }
+
+    // Hide the elements having a class or an id matched by a generic filter.
+    // Only the classes and ids that were not seen before are sent to the adblocker.
+    fn hide_generic_elements<I: Iterator<Item=DOMElement>>(&mut self, elements: I) {
+        let mut classes = vec![];
+        let mut ids = vec![];
+        for element in elements {
+            if let Some(id) = element.id() {
+                let id = id.to_string();
+                if !id.is_empty() && id != HINTS_ID && self.model.cosmetic_ids.insert(id.clone()) {
+                    ids.push(id);
+                }
+            }
+            if let Some(class_name) = element.class_name() {
+                for class in class_name.split_whitespace() {
+                    if self.model.cosmetic_classes.insert(class.to_string()) {
+                        classes.push(class.to_string());
+                    }
+                }
+            }
+        }
+
+        if classes.is_empty() && ids.is_empty() {
+            return;
+        }
+
+        let exceptions = &self.model.cosmetic_exceptions;
+        let selectors = ADBLOCKER.with(|adblocker| adblocker.hidden_class_id_selectors(&classes, &ids, exceptions));
+        self.add_stylesheet(hide_css(selectors.iter()));
+    }
+}
+
+// Create the CSS hiding the elements matched by the selectors.
+fn hide_css<'a, I: Iterator<Item=&'a String>>(selectors: I) -> String {
+    let mut css = String::new();
+    for selector in selectors {
+        css.push_str(&format!("{} {{ display: none !important; }}\n", selector));
+    }
+    css
+}
@@ -0,0 +1,128 @@
+/*
+ * Copyright (c) 2016-2023 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Fill the login forms, including the ones where the password input appears after the username
+//! was submitted, and warn about the sensitive inputs on insecure pages.
+
+use std::time::{Duration, Instant};
+
+use titanium_common::InnerMessage::{Credentials, InsecureForm};
+use titanium_common::urls::host;
+use webkit2gtk_webextension::traits::WebPageExt;
+
+use executor::Executor;
+use login_form::{
+    has_sensitive_input,
+    load_credentials,
+    load_new_password,
+    load_password,
+    submit_login_form,
+};
+
+/// Time during which the password of a multi-step login is waiting for its password input.
+/// After that, it is not filled to avoid filling other forms of the site, like a change-password
+/// form.
+const PENDING_PASSWORD_TIMEOUT: Duration = Duration::from_secs(30);
+
+impl Executor {
+    /// Tell the UI process when the page is not using HTTPS and contains a password or credit
+    /// card input.
+    pub fn check_insecure_form(&mut self) {
+        let is_insecure_page = self.model.page.uri()
+            .map(|url| url.starts_with("http://"))
+            .unwrap_or(false);
+        if self.model.insecure_form_sent || !is_insecure_page {
+            return;
+        }
+        let document = get_document!(self);
+        if has_sensitive_input(&document) {
+            self.model.insecure_form_sent = true;
+            self.send(InsecureForm());
+        }
+    }
+
+    /// Fill the pending password and check if the page became insecure when elements are inserted.
+    pub fn check_inserted_inputs(&mut self) {
+        if self.model.pending_password.is_some() {
+            self.fill_pending_password();
+        }
+        self.check_insecure_form();
+    }
+
+    /// Load the password waiting for the second step of a login if a password input is now in
+    /// the page.
+    pub fn fill_pending_password(&mut self) {
+        let (host, password, time) = unwrap_opt_or_ret!(self.model.pending_password.take(), ());
+        // Never send the password to another site, nor to a form shown long after the login.
+        if self.page_host() != Some(host.clone()) || time.elapsed() > PENDING_PASSWORD_TIMEOUT {
+            self.model.pending_submit = false;
+            return;
+        }
+        let document = get_document!(self);
+        if load_password(&document, &password) {
+            if self.model.pending_submit {
+                self.model.pending_submit = false;
+                submit_login_form(&document);
+            }
+        }
+        else {
+            self.model.pending_password = Some((host, password, time));
+        }
+    }
+
+    /// Load the generated password in the form and send the credentials to save them.
+    pub fn load_generated_password(&mut self, password: &str) {
+        let document = get_document!(self);
+        if load_new_password(&document, password) {
+            self.send_credentials();
+        }
+        else {
+            self.send(Credentials(String::new(), String::new()));
+        }
+    }
+
+    /// Load the username and the password in the login form.
+    /// If there is no password input, the password is loaded when it appears.
+    pub fn load_username_pass(&mut self, username: &str, password: &str) {
+        let document = get_document!(self);
+        self.model.pending_submit = false;
+        if load_credentials(&document, username, password) {
+            self.model.pending_password = None;
+        }
+        else if let Some(host) = self.page_host() {
+            self.model.pending_password = Some((host, password.to_string(), Instant::now()));
+        }
+    }
+
+    /// Submit the login form.
+    pub fn submit_login_form(&mut self) {
+        let document = get_document!(self);
+        submit_login_form(&document);
+        if self.model.pending_password.is_some() {
+            self.model.pending_submit = true;
+        }
+    }
+
+    fn page_host(&self) -> Option<String> {
+        self.model.page.uri()
+            .and_then(|url| host(&url))
+    }
+}
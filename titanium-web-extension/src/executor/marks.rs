@@ -19,30 +19,242 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
-use titanium_common::Percentage::{All, Percent};
+use std::collections::HashMap;
 
+use webkit2gtk_webextension::{
+    traits::{
+        DOMClientRectExt,
+        DOMDocumentExt,
+        DOMDOMWindowExt,
+        DOMElementExt,
+        WebPageExt,
+    },
+    DOMDocument,
+    DOMElement,
+};
+
+use titanium_common::LAST_MARK;
+
+use dom::NodeIter;
 use executor::Executor;
 
+/// The maximum number of positions in a jump list.
+const JUMP_LIST_SIZE: usize = 100;
+
+/// The previous positions in a page.
+#[derive(Default)]
+pub struct JumpList {
+    /// The index of the current position in `marks`.
+    /// It is equal to the length of `marks` when the current position is not in the list.
+    index: usize,
+    marks: Vec<Mark>,
+}
+
+impl JumpList {
+    /// Add a position at the end of the list, forgetting the oldest one when the list is full.
+    fn push(&mut self, mark: Mark) {
+        self.marks.push(mark);
+        if self.marks.len() > JUMP_LIST_SIZE {
+            self.marks.remove(0);
+            self.index = self.index.saturating_sub(1);
+        }
+    }
+}
+
+/// A position in the page.
+#[derive(Clone)]
+pub struct Mark {
+    /// The id of the element nearest to the top of the viewport and its offset from the top of the
+    /// viewport.
+    /// This allows going to the right position even when the content above it changed.
+    anchor: Option<(String, f32)>,
+    x: i64,
+    y: i64,
+}
+
 impl Executor {
+    /// Save the current position in the jump list and in the last mark.
+    pub fn add_jump(&mut self) {
+        let mark = wtry_opt_no_ret!(self.current_position());
+        let url = wtry_opt_no_ret!(self.marks_url());
+        self.model.marks.entry(url.clone())
+            .or_insert_with(HashMap::new)
+            .insert(LAST_MARK, mark.clone());
+        let jump_list = self.model.jump_lists.entry(url).or_insert_with(JumpList::default);
+        // Jumping after going back in the list forgets the positions that were after.
+        jump_list.marks.truncate(jump_list.index);
+        jump_list.push(mark);
+        jump_list.index = jump_list.marks.len();
+    }
+
     pub fn add_mark(&mut self, char: u8) {
-        let percentage = self.scroll_percentage();
-        let percentage =
-            match percentage {
-                All => 0,
-                Percent(percent) => percent,
-            };
-        self.model.marks.insert(char, percentage as u32);
+        let mark = wtry_opt_no_ret!(self.current_position());
+        let url = wtry_opt_no_ret!(self.marks_url());
+        self.model.marks.entry(url)
+            .or_insert_with(HashMap::new)
+            .insert(char, mark);
     }
 
-    pub fn go_to_mark(&mut self, mark: u8) {
-        if let Some(&percent) = self.model.marks.get(&mark) {
-            self.scroll_to_percent(percent);
+    pub fn go_to_mark(&mut self, char: u8) {
+        let url = wtry_opt_no_ret!(self.marks_url());
+        let mark = self.model.marks.get(&url)
+            .and_then(|marks| marks.get(&char))
+            .cloned();
+        if let Some(mark) = mark {
+            self.add_jump();
+            self.scroll_to_mark(&mark);
         }
         // TODO: warn if the mark does not exist?
     }
 
+    /// Go to the previous position in the jump list.
+    pub fn jump_backward(&mut self) {
+        let current_position = wtry_opt_no_ret!(self.current_position());
+        let url = wtry_opt_no_ret!(self.marks_url());
+        let mark =
+            match self.model.jump_lists.get_mut(&url) {
+                Some(jump_list) if jump_list.index > 0 => {
+                    if jump_list.index == jump_list.marks.len() {
+                        // Save the current position to be able to go forward to it.
+                        jump_list.push(current_position);
+                    }
+                    jump_list.index -= 1;
+                    jump_list.marks[jump_list.index].clone()
+                },
+                _ => return,
+            };
+        self.scroll_to_mark(&mark);
+    }
+
+    /// Go to the next position in the jump list.
+    pub fn jump_forward(&mut self) {
+        let url = wtry_opt_no_ret!(self.marks_url());
+        let mark =
+            match self.model.jump_lists.get_mut(&url) {
+                Some(jump_list) if jump_list.index + 1 < jump_list.marks.len() => {
+                    jump_list.index += 1;
+                    jump_list.marks[jump_list.index].clone()
+                },
+                _ => return,
+            };
+        self.scroll_to_mark(&mark);
+    }
+
     pub fn reset_marks(&mut self) {
-        self.model.marks.clear();
-        // TODO: reset the marks on page load.
+        if let Some(url) = self.marks_url() {
+            self.model.marks.remove(&url);
+        }
+    }
+
+    // Get the current scroll position.
+    fn current_position(&self) -> Option<Mark> {
+        let document = self.model.page.dom_document()?;
+        let (x, y) =
+            match self.scrolled_element() {
+                Some(element) => (element.scroll_left(), element.scroll_top()),
+                None => {
+                    let window = document.default_view()?;
+                    (window.scroll_x(), window.scroll_y())
+                },
+            };
+        Some(Mark {
+            anchor: nearest_anchor(&document),
+            x,
+            y,
+        })
+    }
+
+    // Get the URL of the page without the fragment.
+    // The marks are kept by URL, so that they are reset when another page is loaded, but not when
+    // the page is reloaded.
+    fn marks_url(&self) -> Option<String> {
+        let url: String = self.model.page.uri()?.into();
+        Some(url.split('#').next().unwrap_or_default().to_string())
+    }
+
+    // Get the element that is scrolled instead of the window, if any.
+    fn scrolled_element(&self) -> Option<DOMElement> {
+        self.model.scroll_element.as_ref()
+            .filter(|element| element.tag_name().map(Into::into) != Some("BODY".to_string()))
+            .cloned()
+    }
+
+    fn scroll_to_mark(&self, mark: &Mark) {
+        let document = get_document!(self);
+        // Prefer the position relative to the anchor since the page height might have changed.
+        let anchor_delta = mark.anchor.as_ref().and_then(|&(ref id, offset)| {
+            let element = document.element_by_id(id)?;
+            let rect = element.bounding_client_rect()?;
+            Some((rect.top() - offset) as i64)
+        });
+        match self.scrolled_element() {
+            Some(element) => {
+                let y = anchor_delta.map(|delta| element.scroll_top() + delta).unwrap_or(mark.y);
+                element.set_scroll_left(mark.x);
+                element.set_scroll_top(y);
+            },
+            None => {
+                let window = wtry_opt_no_ret!(document.default_view());
+                let y = anchor_delta.map(|delta| window.scroll_y() + delta).unwrap_or(mark.y);
+                window.scroll_to(mark.x as f64, y as f64);
+            },
+        }
+    }
+}
+
+/// Find the element with an id nearest to the top of the viewport and its offset from the top of
+/// the viewport.
+fn nearest_anchor(document: &DOMDocument) -> Option<(String, f32)> {
+    let mut nearest: Option<(String, f32)> = None;
+    let elements = NodeIter::new(document.query_selector_all("[id]").ok());
+    for element in elements {
+        let id: String = element.id().map(Into::into).unwrap_or_default();
+        if id.is_empty() || id.starts_with("__titanium") {
+            continue;
+        }
+        if let Some(rect) = element.bounding_client_rect() {
+            // Skip the hidden elements.
+            if rect.width() == 0.0 && rect.height() == 0.0 {
+                continue;
+            }
+            let top = rect.top();
+            let is_nearer = nearest.as_ref()
+                .map(|&(_, nearest_top)| top.abs() < nearest_top.abs())
+                .unwrap_or(true);
+            if is_nearer {
+                nearest = Some((id, top));
+            }
+        }
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JUMP_LIST_SIZE, JumpList, Mark};
+
+    fn mark(y: i64) -> Mark {
+        Mark {
+            anchor: None,
+            x: 0,
+            y,
+        }
+    }
+
+    #[test]
+    fn jump_list_size() {
+        let mut jump_list = JumpList::default();
+        for y in 0..JUMP_LIST_SIZE as i64 {
+            jump_list.push(mark(y));
+        }
+        jump_list.index = jump_list.marks.len();
+
+        // Going back from the end saves the current position, which forgets the oldest one.
+        jump_list.push(mark(JUMP_LIST_SIZE as i64));
+        assert_eq!(jump_list.marks.len(), JUMP_LIST_SIZE);
+        assert_eq!(jump_list.marks[0].y, 1);
+        assert_eq!(jump_list.index, JUMP_LIST_SIZE - 1);
+        assert_eq!(jump_list.marks[jump_list.index].y, JUMP_LIST_SIZE as i64);
+        assert_eq!(jump_list.marks[jump_list.index - 1].y, JUMP_LIST_SIZE as i64 - 1);
     }
 }
@@ -31,17 +31,21 @@ macro_rules! get_document {
     }};
 }
 
+mod cosmetic;
+mod login;
 mod marks;
 mod scroll;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::f32;
 use std::sync::Mutex;
+use std::time::Instant;
 
 use gio::Cancellable;
 use glib::{Cast, Closure, ObjectExt, ToVariant};
 use regex::Regex;
 use relm::{Relm, Update, UpdateNew};
+use url::percent_encoding::{SIMPLE_ENCODE_SET, utf8_percent_encode};
 use webkit2gtk_webextension::{
     traits::{
         DOMDocumentExt,
@@ -50,12 +54,15 @@ use webkit2gtk_webextension::{
         DOMElementExt,
         DOMEventTargetExt,
         DOMHTMLElementExt,
+        DOMHTMLImageElementExt,
         DOMHTMLInputElementExt,
+        DOMHTMLTextAreaElementExt,
         DOMNodeExt,
         WebPageExt,
     },
     DOMElement,
     DOMHTMLElement,
+    DOMHTMLImageElement,
     DOMHTMLInputElement,
     DOMHTMLSelectElement,
     DOMHTMLTextAreaElement,
@@ -63,17 +70,22 @@ use webkit2gtk_webextension::{
     WebPage,
 };
 
-use titanium_common::{FollowMode, InnerMessage, protocol::encode};
+use titanium_common::{ELEMENTS_INSERTED_EVENT, FollowMode, HintLabels, InnerMessage, protocol::encode};
 use titanium_common::Action::{
     self,
     CopyLink,
+    CopyText,
     DownloadLink,
     FileInput,
     GoInInsertMode,
     NoAction,
+    OpenInBackgroundWindow,
+    OpenInPrivateWindow,
+    OpenUrl,
 };
 use titanium_common::InnerMessage::*;
 
+use adblocker::ADBLOCKER;
 use dom::{
     get_body,
     get_elements_by_tag_name_in_all_frames,
@@ -89,28 +101,48 @@ use dom::{
     mouse_over,
     match_pattern,
 };
-use hints::{create_hints, hide_unrelevant_hints, show_all_hints, HINTS_ID};
-use login_form::{get_credentials, load_password, load_username, submit_login_form};
+use hints::{HintKey, HintedElement, create_hints, hint_key, label_chars, matching_hints, show_only_hints, HINTS_ID};
+use login_form::get_credentials;
+use option_util::OptionExt;
+use self::marks::{JumpList, Mark};
 use self::Msg::*;
 
+define_encode_set! {
+    /// The characters encoded in the fragment of the anchor URLs.
+    pub FRAGMENT_ENCODE_SET = [SIMPLE_ENCODE_SET] | {' ', '"', '#', '%', '<', '>', '`'}
+}
+
 pub struct Executor {
     model: Model,
 }
 
 pub struct Model {
     activated_file_input: Option<DOMHTMLInputElement>,
-    hint_keys: String,
-    hint_map: HashMap<String, DOMElement>,
+    cosmetic_classes: HashSet<String>,
+    cosmetic_exceptions: HashSet<String>,
+    cosmetic_ids: HashSet<String>,
+    generic_hide: bool,
+    hint_chars: String,
+    hint_keys: Vec<HintKey>,
+    hint_map: HashMap<String, HintedElement>,
+    /// Whether the keys filter the hints by their text instead of selecting their labels.
+    hint_text_filter: bool,
+    insecure_form_sent: bool,
+    jump_lists: HashMap<String, JumpList>, // Jump lists by URL.
     last_hovered_element: Option<DOMElement>,
-    marks: HashMap<u8, u32>, // Byte to percent.
+    marks: HashMap<String, HashMap<u8, Mark>>, // Marks by URL.
     page: WebPage,
+    pending_password: Option<(String, String, Instant)>, // Host, password and time of the login.
+    pending_submit: bool,
     relm: Relm<Executor>,
     scroll_element: Option<DOMElement>,
 }
 
 #[derive(Msg)]
 pub enum Msg {
+    Blocked(String, String, bool),
     DocumentLoaded,
+    ElementsInserted,
     MessageRecv(InnerMessage),
     Scroll,
 }
@@ -123,11 +155,21 @@ impl Update for Executor {
     fn model(relm: &Relm<Self>, page: WebPage) -> Model {
         Model {
             activated_file_input: None,
-            hint_keys: String::new(),
+            cosmetic_classes: HashSet::new(),
+            cosmetic_exceptions: HashSet::new(),
+            cosmetic_ids: HashSet::new(),
+            generic_hide: false,
+            hint_chars: String::new(),
+            hint_keys: vec![],
             hint_map: HashMap::new(),
+            hint_text_filter: false,
+            insecure_form_sent: false,
+            jump_lists: HashMap::new(),
             last_hovered_element: None,
             marks: HashMap::new(),
             page,
+            pending_password: None,
+            pending_submit: false,
             relm: relm.clone(),
             scroll_element: None,
         }
@@ -135,7 +177,15 @@ impl Update for Executor {
 
     fn update(&mut self, message: Msg) {
         match message {
+            Blocked(url, rule, is_document) => self.send(RequestBlocked(url, rule, is_document)),
             DocumentLoaded => {
+                self.watch_inserted_elements();
+                self.apply_cosmetic_filters();
+                self.model.insecure_form_sent = false;
+                self.check_insecure_form();
+                if self.model.pending_password.is_some() {
+                    self.fill_pending_password();
+                }
                 self.init_scroll_element();
                 self.send_scroll_percentage();
 
@@ -156,10 +206,16 @@ impl Update for Executor {
                     element.add_event_listener_with_closure("scroll", &handler, false);
                 }
             },
+            ElementsInserted => {
+                self.hide_inserted_elements();
+                self.check_inserted_inputs();
+            },
             MessageRecv(msg) =>
                 match msg {
                     ActivateHint(follow_mode, ctrl_key) => self.activate_hint(follow_mode, ctrl_key),
                     ActivateSelection() => self.activate_selection(),
+                    AddJump() => self.add_jump(),
+                    AllowRequestOnce(url) => ADBLOCKER.with(|adblocker| adblocker.allow_once(&url)),
                     ClickNextPage() => self.click_next_page(),
                     ClickPrevPage() => self.click_prev_page(),
                     EnterHintKey(key) => self.enter_hint_key(key),
@@ -168,8 +224,14 @@ impl Update for Executor {
                     GoToMark(mark) => self.go_to_mark(mark),
                     HideHints() => self.hide_hints(),
                     InsertText(text) => self.insert_text(&text),
+                    JumpBackward() => self.jump_backward(),
+                    JumpForward() => self.jump_forward(),
+                    LoadGeneratedPassword(password) => self.load_generated_password(&password),
                     LoadUsernamePass(username, password) => self.load_username_pass(&username, &password),
                     Mark(char) => self.add_mark(char),
+                    ReloadAdblockLists() => ADBLOCKER.with(|adblocker| adblocker.reload_lists()),
+                    ReloadAdblockWhitelist() => ADBLOCKER.with(|adblocker| adblocker.reload_whitelist()),
+                    RemoveHintKey() => self.remove_hint_key(),
                     ResetMarks() => self.reset_marks(),
                     ResetScrollElement() => self.reset_scroll_element(),
                     ScrollBy(pixels) => self.scroll_by(pixels),
@@ -177,8 +239,10 @@ impl Update for Executor {
                     ScrollTop() => self.scroll_top(),
                     ScrollToPercent(percent) => self.scroll_to_percent(percent),
                     SelectFile(file) => self.select_file(&file),
-                    ShowHints(hint_chars) => self.show_hints(&hint_chars),
+                    ShowHints(hint_chars, hint_labels, follow_mode) =>
+                        self.show_hints(&hint_chars, hint_labels, follow_mode),
                     SubmitLoginForm() => self.submit_login_form(),
+                    ToggleHintText() => self.model.hint_text_filter = !self.model.hint_text_filter,
                     _ => warn!("Unexpected message received: {:?}", msg),
                 },
             Scroll => self.send_scroll_percentage(),
@@ -197,23 +261,42 @@ impl UpdateNew for Executor {
 impl Executor {
     // Activate (click, focus, hover) the selected hint.
     fn activate_hint(&mut self, follow_mode: FollowMode, ctrl_key: bool) {
-        let element = self.model.hint_map.get(&self.model.hint_keys)
-            .and_then(|element| element.clone().downcast::<DOMHTMLElement>().ok());
-        match element {
-            Some(element) => {
-                self.hide_hints();
-                self.model.hint_map.clear();
-                self.model.hint_keys.clear();
+        // The hint is selected when it is the only one matching the typed keys.
+        let mut labels = matching_hints(&self.model.hint_map, &self.model.hint_keys);
+        let label = if labels.len() == 1 { labels.pop() } else { None };
+        let element = label.as_ref()
+            .and_then(|label| self.model.hint_map.get(label))
+            .and_then(|hint| hint.element.clone().downcast::<DOMHTMLElement>().ok());
+        match (label, element) {
+            (Some(label), Some(element)) => {
+                // In rapid mode, the hints stay shown to select other links.
+                if follow_mode == FollowMode::Rapid {
+                    self.mark_hint_used(&label);
+                }
+                else {
+                    self.hide_hints();
+                    self.model.hint_map.clear();
+                    self.model.hint_keys.clear();
+                }
                 let action =
                     match follow_mode {
+                        FollowMode::Anchor => self.go_to_anchor(element),
                         FollowMode::Click => self.click(element, ctrl_key),
+                        FollowMode::CopyAnchor => self.copy_anchor(element),
                         FollowMode::CopyLink => self.copy_link(element),
+                        FollowMode::CopyText => self.copy_text(element),
                         FollowMode::Download => self.download_link(element),
+                        FollowMode::DownloadImage => self.download_image(element),
+                        FollowMode::Focus => self.focus(element),
                         FollowMode::Hover => self.hover(element),
+                        FollowMode::PrivateWindow => self.open_in_private_window(element),
+                        FollowMode::Rapid => self.open_in_background_window(element),
+                        FollowMode::ScrollIntoView => self.scroll_into_view(element),
+                        FollowMode::Select => self.select(element),
                     };
                 self.send(ActivateAction(action));
             },
-            None => self.send(ActivateAction(NoAction)),
+            _ => self.send(ActivateAction(NoAction)),
         }
     }
 
@@ -231,6 +314,16 @@ impl Executor {
         }
     }
 
+    // Get the URL of the current page with the anchor (id or name) of the element as fragment.
+    fn anchor_url(&self, element: &DOMHTMLElement) -> Option<String> {
+        let anchor: String = element.id().map(Into::into)
+            .filter(|id: &String| !id.is_empty())
+            .or_else(|| element.attribute("name").map(Into::into))?;
+        let url: String = self.model.page.uri()?.into();
+        let url = url.split('#').next().unwrap_or_default();
+        Some(format!("{}#{}", url, utf8_percent_encode(&anchor, FRAGMENT_ENCODE_SET)))
+    }
+
     fn click(&mut self, element: DOMHTMLElement, ctrl_key: bool) -> Action {
         if let Ok(input_element) = element.clone().downcast::<DOMHTMLInputElement>() {
             let input_type = input_element.input_type().map(|string| string.to_string()).unwrap_or_default();
@@ -271,11 +364,26 @@ impl Executor {
         }
     }
 
+    fn copy_anchor(&self, element: DOMHTMLElement) -> Action {
+        let url = unwrap_opt_or_ret!(self.anchor_url(&element), NoAction);
+        CopyLink(url)
+    }
+
     fn copy_link(&self, element: DOMHTMLElement) -> Action {
         let href = unwrap_opt_or_ret!(get_href(&element), NoAction);
         CopyLink(href)
     }
 
+    fn copy_text(&self, element: DOMHTMLElement) -> Action {
+        let text =
+            match element.clone().downcast::<DOMHTMLInputElement>() {
+                Ok(input_element) => input_element.value(),
+                Err(_) => element.text_content(),
+            };
+        let text: String = unwrap_opt_or_ret!(text, NoAction).into();
+        CopyText(text.trim().to_string())
+    }
+
     fn click_next_page(&mut self) {
         let regex = Regex::new(r"(?i:next|forward|older|more|›|»)|(?:<.+>)>(?:<.+>)").unwrap();
 
@@ -310,28 +418,59 @@ impl Executor {
         DownloadLink(href)
     }
 
+    // Download the image of the element or the first image it contains (e.g. a link on an image).
+    fn download_image(&self, element: DOMHTMLElement) -> Action {
+        let image =
+            if element.is::<DOMHTMLImageElement>() {
+                Some(element.upcast::<DOMElement>())
+            }
+            else {
+                element.query_selector("img").flatten()
+            };
+        let src = unwrap_opt_or_ret!(image
+            .and_then(|image| image.downcast::<DOMHTMLImageElement>().ok())
+            .and_then(|image| image.src()), NoAction);
+        DownloadLink(src.into())
+    }
+
     // Handle the key press event for the hint mode.
-    // This hides the hints that are not relevant anymore.
+    // The key is added to the hint label when it continues the label of a shown hint, otherwise
+    // it is added to the text used to filter the hints.
+    // This hides the hints that are not relevant anymore and activates the hint when only one
+    // is left.
     fn enter_hint_key(&mut self, key: char) {
-        self.model.hint_keys.push(key);
-        let element = self.model.hint_map.get(&self.model.hint_keys)
-            .and_then(|element| element.clone().downcast::<DOMHTMLElement>().ok());
-        // If no element is found, hide the unrelevant hints.
-        match element {
-            Some(element) => {
-                // TODO: perhaps it'd involve less message if we remove the ActivateHint message.
-                self.send(ClickHintElement(get_href(&element)));
+        self.model.hint_keys.push(hint_key(key, &self.model.hint_chars, self.model.hint_text_filter));
+        let labels = matching_hints(&self.model.hint_map, &self.model.hint_keys);
+        match labels.len() {
+            0 => {
+                // No hint matches the typed keys: ignore the last one.
+                self.model.hint_keys.pop();
             },
-            _ => {
-                let document = self.model.page.dom_document();
-                if let Some(document) = document {
-                    let all_hidden = hide_unrelevant_hints(&document, &self.model.hint_keys);
-                    if all_hidden {
-                        self.model.hint_keys.clear();
-                        show_all_hints(&document);
-                    }
+            1 => {
+                let element = self.model.hint_map.get(&labels[0])
+                    .and_then(|hint| hint.element.clone().downcast::<DOMHTMLElement>().ok());
+                if let Some(element) = element {
+                    // TODO: perhaps it'd involve less message if we remove the ActivateHint message.
+                    self.send(ClickHintElement(get_href(&element)));
                 }
-            }
+            },
+            _ => {
+                let document = wtry_opt_no_ret!(self.model.page.dom_document());
+                show_only_hints(&document, &self.model.hint_map, &labels);
+            },
+        }
+    }
+
+    // Focus the element without clicking on it.
+    fn focus(&self, element: DOMHTMLElement) -> Action {
+        element.focus();
+        let is_text_input_element =
+            element.is::<DOMHTMLInputElement>() && is_text_input(&element.clone().upcast());
+        if is_text_input_element || element.is::<DOMHTMLTextAreaElement>() {
+            GoInInsertMode
+        }
+        else {
+            NoAction
         }
     }
 
@@ -361,6 +500,7 @@ impl Executor {
             }
 
             if let Some(element) = element_to_focus {
+                self.add_jump();
                 element.focus();
                 element.scroll_into_view_if_needed(false);
                 self.send(EnterInsertMode());
@@ -368,6 +508,17 @@ impl Executor {
         }
     }
 
+    // Scroll to the element and set its anchor as the URL fragment.
+    fn go_to_anchor(&self, element: DOMHTMLElement) -> Action {
+        let url = self.anchor_url(&element);
+        element.scroll_into_view(true);
+        match url {
+            Some(url) => OpenUrl(url),
+            // A heading without an anchor can only be scrolled to.
+            None => NoAction,
+        }
+    }
+
     // Hide all the hints.
     fn hide_hints(&self) {
         let elements =
@@ -395,11 +546,58 @@ impl Executor {
         element.set_value(text);
     }
 
-    // Load the username and the password in the login form.
-    fn load_username_pass(&self, username: &str, password: &str) {
+    // Show that the hint `label` was used and show all the hints again to select another one.
+    fn mark_hint_used(&mut self, label: &str) {
         let document = get_document!(self);
-        load_username(&document, username);
-        load_password(&document, password);
+        if let Some(hint) = document.element_by_id(&format!("__titanium_hint_{}", label)) {
+            hint.set_class_name("__titanium_hint __titanium_hint_used");
+        }
+        self.model.hint_keys.clear();
+        self.model.hint_text_filter = false;
+        let labels: Vec<_> = self.model.hint_map.keys().cloned().collect();
+        show_only_hints(&document, &self.model.hint_map, &labels);
+    }
+
+    fn open_in_background_window(&self, element: DOMHTMLElement) -> Action {
+        let href = unwrap_opt_or_ret!(get_href(&element), NoAction);
+        OpenInBackgroundWindow(href)
+    }
+
+    fn open_in_private_window(&self, element: DOMHTMLElement) -> Action {
+        let href = unwrap_opt_or_ret!(get_href(&element), NoAction);
+        OpenInPrivateWindow(href)
+    }
+
+    // Remove the last character typed in follow mode and show the hints matching again.
+    fn remove_hint_key(&mut self) {
+        self.model.hint_keys.pop();
+        let labels = matching_hints(&self.model.hint_map, &self.model.hint_keys);
+        let document = wtry_opt_no_ret!(self.model.page.dom_document());
+        show_only_hints(&document, &self.model.hint_map, &labels);
+    }
+
+    fn scroll_into_view(&self, element: DOMHTMLElement) -> Action {
+        element.scroll_into_view(true);
+        NoAction
+    }
+
+    // Select the text of the element.
+    fn select(&self, element: DOMHTMLElement) -> Action {
+        if let Ok(input_element) = element.clone().downcast::<DOMHTMLInputElement>() {
+            input_element.select();
+        }
+        else if let Ok(text_area) = element.clone().downcast::<DOMHTMLTextAreaElement>() {
+            text_area.select();
+        }
+        else {
+            let selection = self.model.page.dom_document()
+                .and_then(|document| document.default_view())
+                .and_then(|window| window.selection());
+            if let Some(selection) = selection {
+                selection.select_all_children(&element);
+            }
+        }
+        NoAction
     }
 
     // Set the selected file on the input[type="file"].
@@ -446,18 +644,29 @@ impl Executor {
 
     // Show the hint of elements using the hint characters.
     // TODO: only send the hint characters once, not every time?
-    fn show_hints(&mut self, hint_chars: &str) {
+    fn show_hints(&mut self, hint_chars: &str, hint_labels: HintLabels, follow_mode: FollowMode) {
+        self.model.hint_chars = label_chars(hint_chars, hint_labels).to_string();
         self.model.hint_keys.clear();
+        self.model.hint_text_filter = false;
         let container = wtry_opt_no_ret!(get_hints_container(&self.model.page));
         let document = wtry_opt_no_ret!(self.model.page.dom_document());
-        let (hints, hint_map) = wtry_opt_no_ret!(create_hints(&document, hint_chars));
+        let (hints, hint_map) = wtry_opt_no_ret!(create_hints(&document, hint_chars, hint_labels, follow_mode));
         self.model.hint_map = hint_map;
         check_err!(container.append_child(&hints));
     }
 
-    // Submit the login form.
-    fn submit_login_form(&self) {
+    // Listen for the elements inserted in the document.
+    // The event is sent by the mutation observer script of the UI process, once per batch of
+    // inserted elements.
+    fn watch_inserted_elements(&self) {
         let document = get_document!(self);
-        submit_login_form(&document);
+        let stream = self.model.relm.stream().clone();
+        let stream = Mutex::new(::send_cell::SendCell::new(stream));
+        let handler = Closure::new(move |_| {
+            let stream = stream.lock().unwrap();
+            stream.get().emit(ElementsInserted);
+            None
+        });
+        document.add_event_listener_with_closure(ELEMENTS_INSERTED_EVENT, &handler, false);
     }
 }
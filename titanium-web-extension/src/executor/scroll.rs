@@ -33,7 +33,6 @@ use webkit2gtk_webextension::{
     WebPage,
 };
 
-use titanium_common::LAST_MARK;
 use titanium_common::Percentage::{self, All, Percent};
 
 use dom::{ElementIter, get_body, get_document};
@@ -89,7 +88,7 @@ impl Executor {
 
     /// Scroll to the top of the web page.
     pub fn scroll_top(&mut self) {
-        self.add_mark(LAST_MARK);
+        self.add_jump();
         let document = wtry_opt_no_ret!(self.model.page.dom_document());
         let window = wtry_opt_no_ret!(document.default_view());
         window.scroll_to(0.0, 0.0);
@@ -97,7 +96,7 @@ impl Executor {
 
     /// Scroll to the specified percent of the web page.
     pub fn scroll_to_percent(&mut self, percent: u32) {
-        self.add_mark(LAST_MARK);
+        self.add_jump();
         let document = wtry_opt_no_ret!(self.model.page.dom_document());
         let window = wtry_opt_no_ret!(document.default_view());
         let document = wtry_opt_no_ret!(get_document(&self.model.page));
@@ -21,17 +21,19 @@
 
 use std::collections::HashMap;
 use std::i32;
+use std::vec;
 
 use glib::Cast;
 use webkit2gtk_webextension::{
     traits::{
-        DOMCSSStyleDeclarationExt,
+        DOMClientRectExt,
+        DOMClientRectListExt,
+        DOMDOMWindowExt,
         DOMDocumentExt,
         DOMElementExt,
         DOMHTMLFrameElementExt,
         DOMHTMLIFrameElementExt,
         DOMNodeExt,
-        DOMNodeListExt,
     },
     DOMDocument,
     DOMElement,
@@ -39,42 +41,71 @@ use webkit2gtk_webextension::{
     DOMHTMLIFrameElement,
 };
 
+use titanium_common::{FollowMode, HintLabels};
+
 use dom::{
     NodeIter,
     Pos,
-    get_position,
+    get_frame_offsets,
     hide,
     is_enabled,
-    is_visible,
     show,
 };
 
 pub const HINTS_ID: &'static str = "__titanium_hints";
 
+/// The headings and the elements that can be navigated to using the URL fragment.
+const ANCHOR_ELEMENTS: &'static str = "h1, h2, h3, h4, h5, h6, [id], [name]";
+/// The elements that can be activated by the user.
+const HINTABLE_ELEMENTS: &'static str = "a, button, input:not([type=\"hidden\"]), select, textarea";
+/// The images, for the mode downloading them.
+const IMAGE_ELEMENTS: &'static str = "img";
+/// The elements that can be scrolled to: the blocks of text, the sections of the page and the media.
+const SCROLL_ELEMENTS: &'static str = "a, article, aside, blockquote, button, dd, details, dt, figure, footer, form, \
+    h1, h2, h3, h4, h5, h6, header, iframe, img, input:not([type=\"hidden\"]), li, nav, p, pre, section, select, \
+    table, textarea, video";
+/// The blocks of text and the form fields, for the modes using the text of the elements.
+const TEXT_ELEMENTS: &'static str = "a, blockquote, button, caption, dd, dt, figcaption, h1, h2, h3, h4, h5, h6, \
+    input:not([type=\"hidden\"]), label, li, p, pre, td, textarea, th";
+
+/// The characters used for the labels in numbers mode.
+const NUMBER_CHARS: &'static str = "1234567890";
+/// The characters used for the labels in words mode.
+const WORD_CHARS: &'static str = "abcdefghijklmnopqrstuvwxyz";
+/// The words used for the labels in words mode.
+const WORDS: &'static str = include_str!("hint_words.txt");
+
+/// An element with a hint and its text used to filter the hints.
+pub struct HintedElement {
+    pub element: DOMElement,
+    text: String,
+}
+
+/// A key typed in follow mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HintKey {
+    /// A character of the label of a hint.
+    Label(char),
+    /// A character of the text of the element of a hint.
+    Text(char),
+}
+
 pub struct Hints {
-    hints: HashMap<String, DOMElement>,
-    prefix_indexes: Vec<usize>,
-    prefixes: String,
-    suffix_index: usize,
-    suffixes: String,
+    hints: HashMap<String, HintedElement>,
+    labels: vec::IntoIter<String>,
 }
 
 impl Hints {
-    fn new(count: usize, hint_chars: &str) -> Self {
-        let (prefixes, suffixes) =
-            if count <= hint_chars.len() {
-                (String::new(), hint_chars.to_string())
-            }
-            else {
-                let (prefixes, suffixes) = hint_chars.split_at(hint_chars.len() / 2);
-                (prefixes.to_string(), suffixes.to_string())
+    fn new(count: usize, hint_chars: &str, hint_labels: HintLabels) -> Self {
+        let labels =
+            match hint_labels {
+                HintLabels::Letters => letter_labels(count, hint_chars),
+                HintLabels::Numbers => letter_labels(count, NUMBER_CHARS),
+                HintLabels::Words => word_labels(count),
             };
         Hints {
             hints: HashMap::new(),
-            prefix_indexes: vec![],
-            prefixes: prefixes,
-            suffix_index: 0,
-            suffixes: suffixes,
+            labels: labels.into_iter(),
         }
     }
 
@@ -82,51 +113,149 @@ impl Hints {
     /// Returns the text of that hint.
     fn add(&mut self, element: &DOMElement) -> String {
         let hint = self.generate();
-        self.hints.insert(hint.clone(), element.clone());
+        // The text is only computed once, instead of every time a key is typed.
+        self.hints.insert(hint.clone(), HintedElement {
+            element: element.clone(),
+            text: element_text(element),
+        });
         hint
     }
 
     /// Generate the next hint text.
     fn generate(&mut self) -> String {
-        let suffix = self.suffixes.chars().nth(self.suffix_index).unwrap_or('a');
-        self.suffix_index += 1;
+        self.labels.next().unwrap_or_default()
+    }
+}
 
-        let prefix: String = self.prefix_indexes
-            .iter()
-            .map(|&index| self.prefixes.chars().nth(index).unwrap_or('a'))
-            .collect();
+/// The part of a document shown on the screen.
+/// It is computed once per document to avoid querying the window for every element.
+struct Viewport {
+    height: f32,
+    offset: Pos,
+    width: f32,
+}
 
-        if self.suffix_index >= self.suffixes.len() {
-            self.suffix_index = 0;
-            let mut i = 0;
-            while i < self.prefix_indexes.len() {
-                self.prefix_indexes[i] += 1;
-                if self.prefix_indexes[i] >= self.prefixes.len() {
-                    self.prefix_indexes[i] = 0;
-                }
-                else {
-                    break;
-                }
-                i += 1;
-            }
-            if i >= self.prefix_indexes.len() {
-                self.prefix_indexes.push(0);
-            }
+impl Viewport {
+    /// Get the viewport of a document.
+    /// `scroll` is the scroll position of the main document, which contains the hints.
+    fn new(document: &DOMDocument, scroll: &Pos) -> Option<Self> {
+        let window = document.default_view()?;
+        let frame_offsets = get_frame_offsets(document).unwrap_or(Pos { x: 0.0, y: 0.0 });
+        Some(Viewport {
+            height: window.inner_height() as f32,
+            offset: Pos {
+                x: frame_offsets.x + scroll.x,
+                y: frame_offsets.y + scroll.y,
+            },
+            width: window.inner_width() as f32,
+        })
+    }
+
+    /// Get the position of the hint of an element, relative to the main document.
+    /// Returns None if the element does not intersect the viewport.
+    fn hint_position(&self, element: &DOMElement) -> Option<Pos> {
+        let rects = element.client_rects()?;
+        let rect = rects.item(0)?;
+        let left = rect.left();
+        let top = rect.top();
+        if rect.right() < 0.0 || left >= self.width || rect.bottom() < 0.0 || top >= self.height {
+            return None;
         }
+        Some(Pos {
+            x: left + self.offset.x,
+            y: top + self.offset.y,
+        })
+    }
+}
+
+/// Get the kind of the `key` typed in follow mode.
+/// The characters of the labels select the hints, unless the user switched to filtering the hints
+/// by their text (with `filter_text`), since the letters are often all used by the labels.
+pub fn hint_key(key: char, label_chars: &str, filter_text: bool) -> HintKey {
+    if !filter_text && label_chars.contains(key) {
+        HintKey::Label(key)
+    }
+    else {
+        HintKey::Text(key)
+    }
+}
+
+/// Get the characters that can be typed to select a hint label.
+/// The other characters are used to filter the hints by their text.
+pub fn label_chars(hint_chars: &str, hint_labels: HintLabels) -> &str {
+    match hint_labels {
+        HintLabels::Letters => hint_chars,
+        HintLabels::Numbers => NUMBER_CHARS,
+        HintLabels::Words => WORD_CHARS,
+    }
+}
+
+/// Generate `count` labels using the characters `hint_chars` such that no label is a prefix of
+/// another one.
+/// The labels have the minimal length and as many labels as possible are one character shorter than
+/// the others. The first characters of `hint_chars` are used for the shortest labels.
+fn letter_labels(count: usize, hint_chars: &str) -> Vec<String> {
+    let chars: Vec<char> = hint_chars.chars().collect();
+    if chars.len() < 2 {
+        // Prefix-free labels cannot be generated with less than 2 characters.
+        return letter_labels(count, NUMBER_CHARS);
+    }
 
-        format!("{}{}", prefix, suffix)
+    if count <= chars.len() {
+        return chars.iter().take(count).map(|chr| chr.to_string()).collect();
+    }
+
+    // Find the labels of length L - 1, where L is the minimal length for `count` labels.
+    let mut short_labels = vec![String::new()];
+    while short_labels.len() * chars.len() < count {
+        short_labels = short_labels.iter()
+            .flat_map(|label| chars.iter().map(move |&chr| format!("{}{}", label, chr)))
+            .collect();
+    }
+
+    // Each short label used as a prefix gives chars.len() labels instead of one, so only use the
+    // minimal number of short labels as prefixes.
+    let prefix_count = (count - short_labels.len() + chars.len() - 2) / (chars.len() - 1);
+    let prefixes = short_labels.split_off(short_labels.len() - prefix_count);
+    let long_count = count - short_labels.len();
+    let long_labels = prefixes.iter()
+        .flat_map(|prefix| chars.iter().map(move |&chr| format!("{}{}", prefix, chr)))
+        .take(long_count);
+    short_labels.into_iter().chain(long_labels).collect()
+}
+
+/// Generate `count` labels using short words such that no label is a prefix of another one.
+/// Letter labels are used when there are not enough words.
+fn word_labels(count: usize) -> Vec<String> {
+    let mut words: Vec<&str> = WORDS.lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .collect();
+    // Prefer the shortest words.
+    words.sort_by_key(|word| word.len());
+    let mut labels: Vec<String> = vec![];
+    for word in words {
+        if labels.len() >= count {
+            break;
+        }
+        // Since the words are sorted by length, only the previous labels can be a prefix of this word.
+        if !labels.iter().any(|label| word.starts_with(label.as_str())) {
+            labels.push(word.to_string());
+        }
+    }
+    if labels.len() < count {
+        return letter_labels(count, WORD_CHARS);
     }
+    labels
 }
 
 fn create_hint(document: &DOMDocument, pos: &Pos, hint_text: &str) -> Option<DOMElement> {
     document.create_element("div").ok().and_then(|hint| {
         hint.set_class_name("__titanium_hint");
         hint.set_id(&format!("__titanium_hint_{}", hint_text));
-        let style = wtry_opt!(hint.style());
-        check_err_opt!(style.set_property("position", "absolute", "").ok());
-        check_err_opt!(style.set_property("left", &format!("{}px", pos.x), "").ok());
-        check_err_opt!(style.set_property("top", &format!("{}px", pos.y), "").ok());
-        check_err_opt!(style.set_property("z-index", &i32::MAX.to_string(), "").ok());
+        // Set the whole style at once instead of one property at a time.
+        let style = format!("position: absolute; left: {}px; top: {}px; z-index: {};", pos.x, pos.y, i32::MAX);
+        check_err_opt!(hint.set_attribute("style", &style).ok());
 
         let text = wtry_opt!(document.create_text_node(hint_text));
         check_err_opt!(hint.append_child(&text).ok());
@@ -134,119 +263,164 @@ fn create_hint(document: &DOMDocument, pos: &Pos, hint_text: &str) -> Option<DOM
     })
 }
 
-/// Create the hints over all the elements that can be activated by the user (links, form elements).
-pub fn create_hints(document: &DOMDocument, hint_chars: &str) -> Option<(DOMElement, HashMap<String, DOMElement>)> {
+/// Create the hints over the elements relevant to the follow mode: the elements that can be
+/// activated by the user (links, form elements) or, depending on the mode, the elements that can be
+/// navigated to, the images, the blocks of text or every element that can be scrolled to.
+/// Only the elements intersecting the viewport are hinted.
+pub fn create_hints(document: &DOMDocument, hint_chars: &str, hint_labels: HintLabels, follow_mode: FollowMode)
+    -> Option<(DOMElement, HashMap<String, HintedElement>)>
+{
+    let window = wtry_opt!(document.default_view());
+    let scroll = Pos {
+        x: window.scroll_x() as f32,
+        y: window.scroll_y() as f32,
+    };
+    let elements_to_hint =
+        match follow_mode {
+            // The URL fragment only applies to the main document, so the frames are not included.
+            FollowMode::Anchor | FollowMode::CopyAnchor =>
+                get_elements_to_hint(document, ANCHOR_ELEMENTS, &scroll, false),
+            FollowMode::CopyText | FollowMode::Select =>
+                get_elements_to_hint(document, TEXT_ELEMENTS, &scroll, true),
+            FollowMode::DownloadImage => get_elements_to_hint(document, IMAGE_ELEMENTS, &scroll, true),
+            FollowMode::ScrollIntoView => get_elements_to_hint(document, SCROLL_ELEMENTS, &scroll, true),
+            _ => get_elements_to_hint(document, HINTABLE_ELEMENTS, &scroll, true),
+        };
+
     document.create_element("div").ok().and_then(|hints| {
         hints.set_id(HINTS_ID);
-        let style = wtry_opt!(hints.style());
-        check_err_opt!(style.set_property("position", "absolute", "").ok());
-        check_err_opt!(style.set_property("left", "0", "").ok());
-        check_err_opt!(style.set_property("top", "0", "").ok());
-
-        let elements_to_hint = get_elements_to_hint(document);
-
-        let mut hint_map = Hints::new(elements_to_hint.len(), hint_chars);
-        for element in elements_to_hint {
-            if let Some(pos) = get_position(&element) {
-                // FIXME: adjust the position to avoid showing the hint outside the viewport.
-                let hint = wtry_opt!(create_hint(document, &pos, &hint_map.add(&element)));
-                check_err_opt!(hints.append_child(&hint).ok());
-            }
+        check_err_opt!(hints.set_attribute("style", "position: absolute; left: 0; top: 0;").ok());
+
+        // Build the hints in a fragment to insert them all at once.
+        let fragment = wtry_opt!(document.create_document_fragment());
+        let mut hint_map = Hints::new(elements_to_hint.len(), hint_chars, hint_labels);
+        for (element, pos) in elements_to_hint {
+            // FIXME: adjust the position to avoid showing the hint outside the viewport.
+            let hint = wtry_opt!(create_hint(document, &pos, &hint_map.add(&element)));
+            check_err_opt!(fragment.append_child(&hint).ok());
         }
+        check_err_opt!(hints.append_child(&fragment).ok());
         Some((hints, hint_map.hints))
     })
 }
 
-/// Get the elements to hint.
-fn get_elements_to_hint(document: &DOMDocument) -> Vec<DOMElement> {
-    let mut elements_to_hint = get_hintable_elements(document);
-    elements_to_hint.append(&mut get_input_elements(document));
-    elements_to_hint.append(&mut get_hintable_elements_from_frames(document));
-    elements_to_hint
-}
-
-/// Get the hintable elements, except the input.
-fn get_hintable_elements(document: &DOMDocument) -> Vec<DOMElement> {
-    let mut elements_to_hint = vec![];
-    let tag_names = ["a", "button", "select", "textarea"];
-    for tag_name in &tag_names {
-        let elements = NodeIter::new(document.elements_by_tag_name(tag_name));
-        for element in elements {
-            // Only show the hints for visible elements that are not disabled.
-            // TODO: might not need to check if the element is visible anymore because in this
-            // case, get_position() returns None.
-            if is_visible(document, &element) && is_enabled(&element) {
-                elements_to_hint.push(element);
-            }
-        }
-    }
-    elements_to_hint
-}
-
-/// Get the hintable elements from the frames.
-fn get_hintable_elements_from_frames(document: &DOMDocument) -> Vec<DOMElement> {
+/// Get the elements matching `selector` that intersect the viewport with the position of their
+/// hint.
+/// The positions of all the elements are queried before any hint is inserted in the page, so that
+/// the layout is only computed once.
+fn get_elements_to_hint(document: &DOMDocument, selector: &str, scroll: &Pos, include_frames: bool)
+    -> Vec<(DOMElement, Pos)>
+{
     let mut elements_to_hint = vec![];
+    let viewport = unwrap_opt_or_ret!(Viewport::new(document, scroll), elements_to_hint);
 
-    let iter = NodeIter::new(document.elements_by_tag_name("iframe"));
-    for frame in iter {
-        if let Ok(iframe) = frame.downcast::<DOMHTMLIFrameElement>() {
-            if let Some(iframe_document) = iframe.content_document() {
-                elements_to_hint.append(&mut get_elements_to_hint(&iframe_document));
+    let elements = NodeIter::new(document.query_selector_all(selector).ok());
+    for element in elements {
+        if let Some(pos) = viewport.hint_position(&element) {
+            if is_hintable(&element) {
+                elements_to_hint.push((element, pos));
             }
         }
     }
 
-    let iter = NodeIter::new(document.elements_by_tag_name("frame"));
-    for frame in iter {
-        if let Ok(frame) = frame.downcast::<DOMHTMLFrameElement>() {
-            if let Some(frame_document) = frame.content_document() {
-                elements_to_hint.append(&mut get_elements_to_hint(&frame_document));
+    if include_frames {
+        let frames = NodeIter::new(document.query_selector_all("frame, iframe").ok());
+        for frame in frames {
+            if viewport.hint_position(&frame).is_none() {
+                continue;
+            }
+            let frame_document =
+                match frame.downcast::<DOMHTMLIFrameElement>() {
+                    Ok(iframe) => iframe.content_document(),
+                    Err(frame) => frame.downcast::<DOMHTMLFrameElement>().ok()
+                        .and_then(|frame| frame.content_document()),
+                };
+            if let Some(frame_document) = frame_document {
+                elements_to_hint.append(&mut get_elements_to_hint(&frame_document, selector, scroll, true));
             }
         }
     }
     elements_to_hint
 }
 
-/// Get the hintable input elements.
-fn get_input_elements(document: &DOMDocument) -> Vec<DOMElement> {
-    let mut elements_to_hint = vec![];
-    let form_elements = NodeIter::new(document.elements_by_tag_name("input"));
-    for element in form_elements {
-        if is_visible(document, &element) && is_enabled(&element) &&
-            // Do not show hints for hidden form elements.
-            element.attribute("type").map(Into::into) != Some("hidden".to_string())
-        {
-            elements_to_hint.push(element);
+/// Check that the element is not disabled and is not one of the elements added by titanium.
+fn is_hintable(element: &DOMElement) -> bool {
+    let is_titanium_element = element.id()
+        .map(|id| id.starts_with("__titanium"))
+        .unwrap_or(false);
+    !is_titanium_element && is_enabled(element)
+}
+
+/// Get the text of an element used to filter the hints.
+fn element_text(element: &DOMElement) -> String {
+    let text =
+        if element.tag_name().map(Into::into) == Some("INPUT".to_string()) {
+            ["value", "placeholder", "aria-label"].iter()
+                .filter_map(|attribute| element.attribute(attribute))
+                .map(Into::<String>::into)
+                .find(|text| !text.is_empty())
         }
-    }
-    elements_to_hint
+        else {
+            element.text_content().map(Into::into)
+        };
+    text.unwrap_or_else(String::new).to_lowercase()
 }
 
-/// Hide the hints that does not start with `hint_keys`.
-pub fn hide_unrelevant_hints(document: &DOMDocument, hint_keys: &str) -> bool {
-    let hints_to_hide = document.query_selector_all(
-        &format!(".__titanium_hint:not([id^=\"__titanium_hint_{}\"])", hint_keys))
-        .ok();
-    let hints_len = hints_to_hide.as_ref().map(|hints| hints.length()).unwrap_or(0);
-    let hints = NodeIter::new(hints_to_hide);
-    for hint in hints {
-        hide(&hint);
+/// Get the labels of the hints starting with the label keys of `hint_keys` whose element text
+/// contains the text keys.
+pub fn matching_hints(hint_map: &HashMap<String, HintedElement>, hint_keys: &[HintKey]) -> Vec<String> {
+    let (label_prefix, text) = split_hint_keys(hint_keys);
+    hint_map.iter()
+        .filter(|&(label, hint)|
+            label.starts_with(&label_prefix) && (text.is_empty() || hint.text.contains(&text)))
+        .map(|(label, _)| label.clone())
+        .collect()
+}
+
+/// Show the hints whose label is in `labels` and hide the others.
+pub fn show_only_hints(document: &DOMDocument, hint_map: &HashMap<String, HintedElement>, labels: &[String]) {
+    for label in hint_map.keys() {
+        if let Some(hint) = document.element_by_id(&format!("__titanium_hint_{}", label)) {
+            if labels.contains(label) {
+                show(&hint);
+            }
+            else {
+                hide(&hint);
+            }
+        }
     }
-    let all_hints = unwrap_or_ret!(document.query_selector_all(".__titanium_hint"), false);
-    all_hints.length() == hints_len
 }
 
-/// Show all hints.
-pub fn show_all_hints(document: &DOMDocument) {
-    let hints = NodeIter::new(document.query_selector_all(".__titanium_hint").ok());
-    for hint in hints {
-        show(&hint);
+/// Split the typed keys into the beginning of a label and the lowercase text to search in the
+/// elements.
+fn split_hint_keys(hint_keys: &[HintKey]) -> (String, String) {
+    let mut label = String::new();
+    let mut text = String::new();
+    for key in hint_keys {
+        match *key {
+            HintKey::Label(key) => label.push(key),
+            HintKey::Text(key) => text.extend(key.to_lowercase()),
+        }
     }
+    (label, text)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Hints;
+    use titanium_common::HintLabels;
+
+    use super::{HintKey, Hints, hint_key, letter_labels, split_hint_keys, word_labels};
+
+    fn generate(count: usize, hint_chars: &str, hint_labels: HintLabels) -> Vec<String> {
+        let mut hints = Hints::new(count, hint_chars, hint_labels);
+        (0 .. count).map(|_| hints.generate()).collect()
+    }
+
+    fn is_prefix_free(labels: &[String]) -> bool {
+        labels.iter().enumerate().all(|(index, label)|
+            labels.iter().enumerate()
+                .all(|(other_index, other)| index == other_index || !other.starts_with(label.as_str())))
+    }
 
     #[test]
     fn generate_hints() {
@@ -254,72 +428,139 @@ mod tests {
             "h", "j", "k", "l", "a", "s", "d", "f", "g", "y", "u", "i", "o",
         ];
         let count = expected_hints.len();
-        let mut hints = Hints::new(count, "hjklasdfgyuiopqwertnmzxcvb");
-        let mut hint_texts = vec![];
-        for _ in 0 .. count {
-            hint_texts.push(hints.generate());
-        }
-        assert_eq!(expected_hints, hint_texts);
+        assert_eq!(expected_hints, generate(count, "hjklasdfgyuiopqwertnmzxcvb", HintLabels::Letters));
 
         let expected_hints = vec![
             "h", "j", "k", "l", "a", "s", "d", "f", "g", "y", "u", "i", "o", "p", "q", "w", "e", "r", "t", "n", "m",
             "z", "x", "c", "v", "b",
         ];
         let count = expected_hints.len();
-        let mut hints = Hints::new(count, "hjklasdfgyuiopqwertnmzxcvb");
-        let mut hint_texts = vec![];
-        for _ in 0 .. count {
-            hint_texts.push(hints.generate());
-        }
-        assert_eq!(expected_hints, hint_texts);
+        assert_eq!(expected_hints, generate(count, "hjklasdfgyuiopqwertnmzxcvb", HintLabels::Letters));
 
         let expected_hints = vec![
-            "p", "q", "w", "e", "r", "t", "n", "m", "z", "x", "c", "v", "b",
-            "hp", "hq", "hw", "he", "hr", "ht", "hn", "hm", "hz", "hx", "hc", "hv", "hb",
-            "jp", "jq", "jw", "je", "jr", "jt", "jn", "jm", "jz", "jx", "jc", "jv", "jb",
-            "kp", "kq", "kw", "ke", "kr", "kt", "kn", "km", "kz", "kx", "kc", "kv", "kb",
-            "lp", "lq", "lw", "le", "lr", "lt", "ln", "lm", "lz", "lx", "lc", "lv", "lb",
-            "ap", "aq", "aw", "ae", "ar", "at", "an", "am", "az", "ax", "ac", "av", "ab",
-            "sp", "sq", "sw", "se", "sr", "st", "sn", "sm", "sz", "sx", "sc", "sv", "sb",
-            "dp", "dq", "dw", "de", "dr", "dt", "dn", "dm", "dz", "dx", "dc", "dv", "db",
-            "fp", "fq", "fw", "fe", "fr", "ft", "fn", "fm", "fz", "fx", "fc", "fv", "fb",
-            "gp", "gq", "gw", "ge", "gr", "gt", "gn", "gm", "gz", "gx", "gc", "gv", "gb",
-            "yp", "yq", "yw", "ye", "yr", "yt", "yn", "ym", "yz", "yx", "yc", "yv", "yb",
-            "up", "uq", "uw", "ue", "ur", "ut", "un", "um", "uz", "ux", "uc", "uv", "ub",
-            "ip", "iq", "iw", "ie", "ir", "it", "in", "im", "iz", "ix", "ic", "iv", "ib",
-            "op", "oq", "ow", "oe", "or", "ot", "on", "om", "oz", "ox", "oc", "ov", "ob",
+            "h", "j", "k", "l", "a", "s", "d", "f", "g", "y", "u", "i", "o",
+            "p", "q", "w", "e", "r", "t", "nh", "nj", "nk", "nl", "na", "ns", "nd",
+            "nf", "ng", "ny", "nu", "ni", "no", "np", "nq", "nw", "ne", "nr", "nt", "nn",
+            "nm", "nz", "nx", "nc", "nv", "nb", "mh", "mj", "mk", "ml", "ma", "ms", "md",
+            "mf", "mg", "my", "mu", "mi", "mo", "mp", "mq", "mw", "me", "mr", "mt", "mn",
+            "mm", "mz", "mx", "mc", "mv", "mb", "zh", "zj", "zk", "zl", "za", "zs", "zd",
+            "zf", "zg", "zy", "zu", "zi", "zo", "zp", "zq", "zw", "ze", "zr", "zt", "zn",
+            "zm", "zz", "zx", "zc", "zv", "zb", "xh", "xj", "xk", "xl", "xa", "xs", "xd",
+            "xf", "xg", "xy", "xu", "xi", "xo", "xp", "xq", "xw", "xe", "xr", "xt", "xn",
+            "xm", "xz", "xx", "xc", "xv", "xb", "ch", "cj", "ck", "cl", "ca", "cs", "cd",
+            "cf", "cg", "cy", "cu", "ci", "co", "cp", "cq", "cw", "ce", "cr", "ct", "cn",
+            "cm", "cz", "cx", "cc", "cv", "cb", "vh", "vj", "vk", "vl", "va", "vs", "vd",
+            "vf", "vg", "vy", "vu", "vi", "vo", "vp", "vq", "vw", "ve", "vr", "vt", "vn",
+            "vm", "vz", "vx", "vc", "vv", "vb", "bh", "bj", "bk", "bl", "ba", "bs", "bd",
         ];
         let count = expected_hints.len();
-        let mut hints = Hints::new(count, "hjklasdfgyuiopqwertnmzxcvb");
-        let mut hint_texts = vec![];
-        for _ in 0 .. count {
-            hint_texts.push(hints.generate());
-        }
-        assert_eq!(expected_hints, hint_texts);
+        assert_eq!(expected_hints, generate(count, "hjklasdfgyuiopqwertnmzxcvb", HintLabels::Letters));
 
         let expected_hints = vec![
-            "p", "q", "w", "e", "r", "t", "n", "m", "z", "x", "c", "v", "b",
-            "hp", "hq", "hw", "he", "hr", "ht", "hn", "hm", "hz", "hx", "hc", "hv", "hb",
-            "jp", "jq", "jw", "je", "jr", "jt", "jn", "jm", "jz", "jx", "jc", "jv", "jb",
-            "kp", "kq", "kw", "ke", "kr", "kt", "kn", "km", "kz", "kx", "kc", "kv", "kb",
-            "lp", "lq", "lw", "le", "lr", "lt", "ln", "lm", "lz", "lx", "lc", "lv", "lb",
-            "ap", "aq", "aw", "ae", "ar", "at", "an", "am", "az", "ax", "ac", "av", "ab",
-            "sp", "sq", "sw", "se", "sr", "st", "sn", "sm", "sz", "sx", "sc", "sv", "sb",
-            "dp", "dq", "dw", "de", "dr", "dt", "dn", "dm", "dz", "dx", "dc", "dv", "db",
-            "fp", "fq", "fw", "fe", "fr", "ft", "fn", "fm", "fz", "fx", "fc", "fv", "fb",
-            "gp", "gq", "gw", "ge", "gr", "gt", "gn", "gm", "gz", "gx", "gc", "gv", "gb",
-            "yp", "yq", "yw", "ye", "yr", "yt", "yn", "ym", "yz", "yx", "yc", "yv", "yb",
-            "up", "uq", "uw", "ue", "ur", "ut", "un", "um", "uz", "ux", "uc", "uv", "ub",
-            "ip", "iq", "iw", "ie", "ir", "it", "in", "im", "iz", "ix", "ic", "iv", "ib",
-            "op", "oq", "ow", "oe", "or", "ot", "on", "om", "oz", "ox", "oc", "ov", "ob",
-            "hhp"
+            "h", "j", "k", "l", "a", "s", "d", "f", "g", "y", "u", "i", "o",
+            "p", "q", "w", "e", "r", "t", "nh", "nj", "nk", "nl", "na", "ns", "nd",
+            "nf", "ng", "ny", "nu", "ni", "no", "np", "nq", "nw", "ne", "nr", "nt", "nn",
+            "nm", "nz", "nx", "nc", "nv", "nb", "mh", "mj", "mk", "ml", "ma", "ms", "md",
+            "mf", "mg", "my", "mu", "mi", "mo", "mp", "mq", "mw", "me", "mr", "mt", "mn",
+            "mm", "mz", "mx", "mc", "mv", "mb", "zh", "zj", "zk", "zl", "za", "zs", "zd",
+            "zf", "zg", "zy", "zu", "zi", "zo", "zp", "zq", "zw", "ze", "zr", "zt", "zn",
+            "zm", "zz", "zx", "zc", "zv", "zb", "xh", "xj", "xk", "xl", "xa", "xs", "xd",
+            "xf", "xg", "xy", "xu", "xi", "xo", "xp", "xq", "xw", "xe", "xr", "xt", "xn",
+            "xm", "xz", "xx", "xc", "xv", "xb", "ch", "cj", "ck", "cl", "ca", "cs", "cd",
+            "cf", "cg", "cy", "cu", "ci", "co", "cp", "cq", "cw", "ce", "cr", "ct", "cn",
+            "cm", "cz", "cx", "cc", "cv", "cb", "vh", "vj", "vk", "vl", "va", "vs", "vd",
+            "vf", "vg", "vy", "vu", "vi", "vo", "vp", "vq", "vw", "ve", "vr", "vt", "vn",
+            "vm", "vz", "vx", "vc", "vv", "vb", "bh", "bj", "bk", "bl", "ba", "bs", "bd",
+            "bf",
         ];
         let count = expected_hints.len();
-        let mut hints = Hints::new(count, "hjklasdfgyuiopqwertnmzxcvb");
-        let mut hint_texts = vec![];
-        for _ in 0 .. count {
-            hint_texts.push(hints.generate());
+        assert_eq!(expected_hints, generate(count, "hjklasdfgyuiopqwertnmzxcvb", HintLabels::Letters));
+    }
+
+    #[test]
+    fn generate_letter_hints() {
+        for &count in &[0, 1, 2, 3, 4, 5, 9, 10, 16, 17, 30, 64, 65, 200, 1000] {
+            let labels = letter_labels(count, "asdf");
+            assert_eq!(count, labels.len());
+            assert!(is_prefix_free(&labels));
+            // The labels have the minimal length.
+            let mut max_length = 0;
+            while 4_usize.pow(max_length) < count {
+                max_length += 1;
+            }
+            assert!(labels.iter().all(|label| label.len() as u32 <= max_length.max(1)));
         }
-        assert_eq!(expected_hints, hint_texts);
+
+        assert_eq!(vec!["a", "s", "d", "fa", "fs", "fd"], letter_labels(6, "asdf"));
+        assert_eq!(vec!["a", "s", "da", "ds", "dd", "df", "fa", "fs"], letter_labels(8, "asdf"));
+    }
+
+    #[test]
+    fn generate_number_hints() {
+        let expected_hints = vec!["1", "2", "3", "4", "5", "6", "7", "8", "9", "0"];
+        assert_eq!(expected_hints, generate(10, "hjkl", HintLabels::Numbers));
+
+        let expected_hints = vec!["1", "2", "3", "4", "5", "6", "7", "8", "91", "92", "93", "94", "95", "96", "97", "98",
+            "99", "90", "01", "02"];
+        assert_eq!(expected_hints, generate(20, "hjkl", HintLabels::Numbers));
+
+        let labels = generate(500, "hjkl", HintLabels::Numbers);
+        assert!(is_prefix_free(&labels));
+        assert!(labels.iter().all(|label| label.chars().all(|chr| chr.is_ascii_digit())));
+    }
+
+    #[test]
+    fn generate_word_hints() {
+        let labels = generate(50, "hjkl", HintLabels::Words);
+        assert_eq!(50, labels.len());
+        assert!(is_prefix_free(&labels));
+        assert!(labels.iter().all(|label| label.len() >= 2 && label.chars().all(|chr| chr.is_ascii_lowercase())));
+
+        let labels = word_labels(150);
+        assert_eq!(150, labels.len());
+        assert!(is_prefix_free(&labels));
+
+        // Fallback on letters when there are not enough words.
+        let labels = word_labels(1000);
+        assert_eq!(1000, labels.len());
+        assert!(is_prefix_free(&labels));
+    }
+
+    #[test]
+    fn hint_key_kind() {
+        let hint_chars = "hjklasdfgyuiopqwertnmzxcvb";
+        assert_eq!(hint_chars.len(), 26);
+        // All the lowercase letters are labels...
+        for key in hint_chars.chars() {
+            assert_eq!(hint_key(key, hint_chars, false), HintKey::Label(key));
+        }
+        // ...so only the other characters filter by text...
+        assert_eq!(hint_key('F', hint_chars, false), HintKey::Text('F'));
+        assert_eq!(hint_key('1', hint_chars, false), HintKey::Text('1'));
+        assert_eq!(hint_key('é', hint_chars, false), HintKey::Text('é'));
+        // ...until switching to the text filtering.
+        for key in hint_chars.chars() {
+            assert_eq!(hint_key(key, hint_chars, true), HintKey::Text(key));
+        }
+        assert_eq!(hint_key('F', hint_chars, true), HintKey::Text('F'));
+
+        // With the numeric labels, the letters always filter by text.
+        assert_eq!(hint_key('f', "1234567890", false), HintKey::Text('f'));
+        assert_eq!(hint_key('1', "1234567890", false), HintKey::Label('1'));
+    }
+
+    #[test]
+    fn split_keys() {
+        assert_eq!(split_hint_keys(&[]), (String::new(), String::new()));
+
+        // The text and the label keys can be interleaved.
+        let mut keys = vec![HintKey::Text('F'), HintKey::Label('a'), HintKey::Text('o'), HintKey::Label('s')];
+        assert_eq!(split_hint_keys(&keys), ("as".to_string(), "fo".to_string()));
+
+        // Removing the last key removes the last character typed, whatever its kind.
+        keys.pop();
+        assert_eq!(split_hint_keys(&keys), ("a".to_string(), "fo".to_string()));
+        keys.pop();
+        assert_eq!(split_hint_keys(&keys), ("a".to_string(), "f".to_string()));
     }
 }
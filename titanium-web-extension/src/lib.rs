@@ -44,6 +44,8 @@ extern crate relm;
 extern crate relm_derive;
 extern crate send_cell;
 extern crate titanium_common;
+#[macro_use]
+extern crate url;
 extern crate xdg;
 extern crate webkit2gtk_webextension;
 
@@ -19,6 +19,10 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
+//! Detection of the login forms and filling of their inputs.
+//! The login forms are not always in a <form> element and the username and password inputs can be
+//! on different pages.
+
 use glib::Cast;
 use webkit2gtk_webextension::{
     traits::{
@@ -29,6 +33,7 @@ use webkit2gtk_webextension::{
         DOMNodeExt,
     },
     DOMDocument,
+    DOMElement,
     DOMHTMLFormElement,
     DOMHTMLInputElement,
 };
@@ -36,61 +41,149 @@ use webkit2gtk_webextension::{
 use dom::{
     NodeIter,
     change_event,
+    click,
+    input_event,
     is_hidden,
 };
 use option_util::OptionExt;
 
+/// Selector of the inputs containing sensitive data: passwords and credit card information.
+const SENSITIVE_INPUTS: &str = "input[type='password'], input[autocomplete^='cc-'], input[autocomplete*=' cc-'], \
+    input[name*='cardnumber' i], input[name*='card-number' i], input[name*='card_number' i], input[name*='cvc' i], \
+    input[name*='cvv' i]";
+
+/// Selector of the buttons that could submit a login form.
+const BUTTONS: &str = "button, input[type='submit'], input[type='button'], [role='button']";
+
+/// Selector of the inputs that could contain a username.
+const USERNAME_INPUTS: &str = "input[type='text'], input[type='email'], input[type='tel'], input:not([type])";
+
+/// Minimum score of a username input to consider it is the first step of a login without a
+/// password input.
+const USERNAME_ONLY_MIN_SCORE: u32 = 5;
+
+/// Words found in the text of the buttons submitting a login form.
+const SUBMIT_WORDS: &[&str] = &["connexion", "connecter", "continu", "log in", "login", "next", "sign in", "signin",
+    "submit", "suivant"];
+
+/// Words found in the name, id or label of the username inputs.
+const USERNAME_WORDS: &[&str] = &["account", "courriel", "email", "e-mail", "identifi", "login", "mail", "user",
+    "utilisateur"];
+
 pub struct Credential {
     pub check: bool,
     pub password: String,
     pub username: String,
 }
 
+/// A login form: the inputs might be outside of a <form> element, in which case the container
+/// is their closest common ancestor.
+struct LoginForm {
+    container: DOMElement,
+    password: Option<DOMHTMLInputElement>,
+    username: Option<DOMHTMLInputElement>,
+}
+
+impl LoginForm {
+    /// Create the login form containing the `password` input.
+    fn from_password(document: &DOMDocument, password: DOMElement) -> Self {
+        let container = login_container(&password, "input[type='password']");
+        let username = find_username_input(document, &container, Some(&password), 1);
+        LoginForm {
+            container,
+            password: password.downcast().ok(),
+            username,
+        }
+    }
+}
+
 /// Find a login form.
 /// If a visible form exists, prefer it.
-fn find_login_form(document: &DOMDocument) -> Option<DOMHTMLFormElement> {
-    let elements = NodeIter::new(document.query_selector_all("input[type='password']").ok());
-    let elements_count = elements.len();
-    for input_element in elements {
-        // TODO: check that all elements are hidden instead of checking that there is only one
-        // element.
-        if !is_hidden(document, &input_element) || elements_count == 1 {
-            let mut form_element = None;
-            let mut element = Some(input_element);
-            while let Some(el) = element {
-                if el.tag_name().map(|string| string.to_string()).unwrap_or_default().to_lowercase() == "form" {
-                    form_element = Some(el);
-                    break;
-                }
-                element = el.parent_element();
-            }
-            if let Some(form) = form_element {
-                if let Ok(form) = form.downcast() {
-                    return Some(form);
-                }
-            }
-        }
-        // TODO: also look for invisible login form.
+/// If there is no password input, find the username input of the first step of a login.
+fn find_login_form(document: &DOMDocument) -> Option<LoginForm> {
+    if let Some(password) = find_password_input(document) {
+        return Some(LoginForm::from_password(document, password));
     }
-    None
+    let body = document.body()?.upcast::<DOMElement>();
+    let username = find_username_input(document, &body, None, USERNAME_ONLY_MIN_SCORE)?;
+    let container = login_container(&username.clone().upcast(), USERNAME_INPUTS);
+    Some(LoginForm {
+        container,
+        password: None,
+        username: Some(username),
+    })
 }
 
-/// Find the credentials from the login form of the active element in the document.
-pub fn get_credentials(document: &DOMDocument) -> Option<Credential> {
-    let mut password = String::new();
-    let mut username = String::new();
-    if let Some(login_form) = get_login_form(document) {
-        let username_element = login_form.query_selector("input[type='text']").flatten()
-            .and_then(|element| element.downcast::<DOMHTMLInputElement>().ok());
-        if let Some(element) = username_element {
-            username = element.value().map(Into::into).unwrap_or_default();
+/// Find the login form containing the focused element.
+fn find_active_login_form(document: &DOMDocument) -> Option<LoginForm> {
+    let active_element = document.active_element()?;
+    if is_password_input(&active_element) {
+        return Some(LoginForm::from_password(document, active_element));
+    }
+    let container = login_container(&active_element, "input[type='password']");
+    let password = NodeIter::new(container.query_selector_all("input[type='password']").ok())
+        .find(|input| !is_hidden(document, input))?;
+    Some(LoginForm::from_password(document, password))
+}
+
+/// Find the password input of the login form.
+/// Visible inputs are preferred and inputs for a new password come last.
+fn find_password_input(document: &DOMDocument) -> Option<DOMElement> {
+    let inputs: Vec<_> = NodeIter::new(document.query_selector_all("input[type='password']").ok()).collect();
+    let mut visible_inputs: Vec<_> = inputs.iter()
+        .filter(|input| !is_hidden(document, input))
+        .cloned()
+        .collect();
+    visible_inputs.sort_by_key(|input| attribute(input, "autocomplete") == "new-password");
+    if let Some(input) = visible_inputs.into_iter().next() {
+        return Some(input);
+    }
+    // TODO: check that all elements are hidden instead of checking that there is only one
+    // element.
+    if inputs.len() == 1 {
+        inputs.into_iter().next()
+    }
+    else {
+        None
+    }
+}
+
+/// Find the input which most likely contains the username in `container`.
+/// Only the inputs before the `password` input are considered.
+fn find_username_input(document: &DOMDocument, container: &DOMElement, password: Option<&DOMElement>,
+    min_score: u32) -> Option<DOMHTMLInputElement>
+{
+    let selector = format!("{}, input[type='password']", USERNAME_INPUTS);
+    let mut best_input = None;
+    let mut best_score = 0;
+    for input in NodeIter::new(container.query_selector_all(&selector).ok()) {
+        if Some(&input) == password {
+            break;
+        }
+        if is_password_input(&input) || is_hidden(document, &input) {
+            continue;
         }
-        let password_element = login_form.query_selector("input[type='password']").flatten()
-            .and_then(|element| element.downcast::<DOMHTMLInputElement>().ok());
-        if let Some(element) = password_element {
-            password = element.value().map(Into::into).unwrap_or_default();
+        let score = username_score(document, &input);
+        // On equal scores, the input closest to the password input wins.
+        if score >= min_score && score >= best_score {
+            best_score = score;
+            best_input = Some(input);
         }
     }
+    best_input.and_then(|input| input.downcast().ok())
+}
+
+/// Find the credentials from the login form of the active element in the document.
+pub fn get_credentials(document: &DOMDocument) -> Option<Credential> {
+    let login_form = find_active_login_form(document).or_else(|| find_login_form(document))?;
+    let username = login_form.username
+        .and_then(|input| input.value())
+        .map(Into::into)
+        .unwrap_or_default();
+    let password = login_form.password
+        .and_then(|input| input.value())
+        .map(Into::into)
+        .unwrap_or_default();
     if username.is_empty() || password.is_empty() {
         None
     }
@@ -103,76 +196,247 @@ pub fn get_credentials(document: &DOMDocument) -> Option<Credential> {
     }
 }
 
-/// Get the login form.
-fn get_login_form(document: &DOMDocument) -> Option<DOMHTMLFormElement> {
-    document.active_element()
-        .and_then(|active_element| {
-            let mut form_element = None;
-            let mut element = Some(active_element);
-            while let Some(el) = element {
-                if el.tag_name().map(|string| string.to_string()).unwrap_or_default().to_lowercase() == "form" {
-                    form_element = Some(el);
-                    break;
-                }
-                element = el.parent_element();
-            }
-            if let Some(form) = form_element {
-                return form.downcast().ok();
+/// Check if the document contains a password or credit card input.
+pub fn has_sensitive_input(document: &DOMDocument) -> bool {
+    document.query_selector(SENSITIVE_INPUTS).flatten().is_some()
+}
+
+/// Load the username and the password in the login form.
+/// Return false if there was no password input, which happens in the first step of a multi-step
+/// login.
+pub fn load_credentials(document: &DOMDocument, username: &str, password: &str) -> bool {
+    let login_form = unwrap_opt_or_ret!(find_login_form(document), false);
+    if let Some(ref input) = login_form.username {
+        set_value(input, username);
+    }
+    if let Some(ref input) = login_form.password {
+        set_value(input, password);
+        true
+    }
+    else {
+        false
+    }
+}
+
+/// Load a new password in every visible password input of the form, like the password and its
+/// confirmation in a sign-up form.
+/// Return false if there is no password input.
+pub fn load_new_password(document: &DOMDocument, password: &str) -> bool {
+    let login_form = find_active_login_form(document).or_else(|| find_login_form(document));
+    let login_form = unwrap_opt_or_ret!(login_form, false);
+    let mut filled = false;
+    for input in NodeIter::new(login_form.container.query_selector_all("input[type='password']").ok()) {
+        if !is_hidden(document, &input) {
+            if let Ok(input) = input.downcast::<DOMHTMLInputElement>() {
+                set_value(&input, password);
+                filled = true;
             }
-            None
-        })
+        }
+    }
+    filled
 }
 
 /// Load the password in the login form.
-pub fn load_password(document: &DOMDocument, password: &str) {
-    // TODO: trigger change event (https://stackoverflow.com/questions/23892547/what-is-the-best-way-to-trigger-onchange-event-in-react-js/46012210#46012210):
-    // var nativeInputValueSetter = Object.getOwnPropertyDescriptor(window.HTMLInputElement.prototype, "value").set;
-    // nativeInputValueSetter.call(element, 'react 16 value');
-    // var ev2 = new Event('input', { bubbles: true});
-    // element.dispatchEvent(ev2);
-    let password_inputs =
-        find_login_form(document)
-            .and_then(|login_form| login_form.query_selector_all("input[type='password']").ok());
-    let inputs = NodeIter::new(password_inputs);
-    for input in inputs {
-        if !is_hidden(document, &input) {
-            let password_input = input.clone().downcast::<DOMHTMLInputElement>();
-            if let Ok(password_input) = password_input {
-                password_input.set_value(password);
-                change_event(&input);
-                break;
+/// Return false if there is no password input.
+pub fn load_password(document: &DOMDocument, password: &str) -> bool {
+    let input = find_password_input(document)
+        .and_then(|input| input.downcast::<DOMHTMLInputElement>().ok());
+    if let Some(input) = input {
+        set_value(&input, password);
+        true
+    }
+    else {
+        false
+    }
+}
+
+/// Submit the login form.
+/// The submit button is clicked, if any, so that the JavaScript handlers are triggered.
+pub fn submit_login_form(document: &DOMDocument) {
+    let login_form = unwrap_opt_or_ret!(find_login_form(document), ());
+    if let Some(button) = find_submit_button(document, &login_form.container) {
+        click(&button, false);
+    }
+    else if let Ok(form) = login_form.container.downcast::<DOMHTMLFormElement>() {
+        form.submit();
+    }
+}
+
+/// Get the value of an attribute in lowercase, or an empty string if it does not exist.
+fn attribute(element: &DOMElement, name: &str) -> String {
+    element.attribute(name)
+        .map(|value| value.to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Find the button submitting the login form in the container or its ancestors.
+fn find_submit_button(document: &DOMDocument, container: &DOMElement) -> Option<DOMElement> {
+    let mut element = Some(container.clone());
+    while let Some(el) = element {
+        let buttons: Vec<_> = NodeIter::new(el.query_selector_all(BUTTONS).ok())
+            .filter(|button| !is_hidden(document, button))
+            .collect();
+        let submit_button = buttons.iter()
+            .find(|button| attribute(button, "type") == "submit")
+            .or_else(|| buttons.iter().find(|button| {
+                let text = format!("{} {} {}", button.text_content().map(Into::into).unwrap_or_else(String::new),
+                    attribute(button, "value"), attribute(button, "aria-label"));
+                is_submit_text(&text)
+            }));
+        if let Some(button) = submit_button {
+            return Some(button.clone());
+        }
+        if is_tag(&el, "FORM") || is_tag(&el, "BODY") {
+            break;
+        }
+        element = el.parent_element();
+    }
+    None
+}
+
+fn is_password_input(element: &DOMElement) -> bool {
+    is_tag(element, "INPUT") && attribute(element, "type") == "password"
+}
+
+/// Check if the text of a button looks like the one of a button submitting a login form.
+fn is_submit_text(text: &str) -> bool {
+    let text = text.to_lowercase();
+    SUBMIT_WORDS.iter().any(|word| text.contains(word))
+}
+
+fn is_tag(element: &DOMElement, tag_name: &str) -> bool {
+    element.tag_name().map(|name| name.to_uppercase()) == Some(tag_name.to_string())
+}
+
+/// Get the text of the labels of an input.
+fn label_text(document: &DOMDocument, input: &DOMElement) -> String {
+    let mut text = String::new();
+    let id = input.id().map(Into::into).unwrap_or_else(String::new);
+    if !id.is_empty() && !id.contains('\'') {
+        let label = document.query_selector(&format!("label[for='{}']", id)).flatten();
+        if let Some(label_text) = label.and_then(|label| label.text_content()) {
+            text.push_str(&label_text);
+        }
+    }
+    let mut element = input.parent_element();
+    while let Some(el) = element {
+        if is_tag(&el, "LABEL") {
+            if let Some(label_text) = el.text_content() {
+                text.push(' ');
+                text.push_str(&label_text);
             }
+            break;
+        }
+        if is_tag(&el, "FORM") || is_tag(&el, "BODY") {
+            break;
         }
+        element = el.parent_element();
     }
+    text.to_lowercase()
 }
 
-/// Load the username in the login form.
-pub fn load_username(document: &DOMDocument, username: &str) {
-    // TODO: trigger change event:
-    let username_inputs =
-        find_login_form(document)
-            .and_then(|login_form|
-                // FIXME: check for more types than just text and email.
-                // TODO: allow no type attribute.
-                login_form.query_selector_all("input[type='text'], input[type='email'], input:not([type])").ok()
-            );
-    let inputs = NodeIter::new(username_inputs);
-    for input in inputs {
-        if !is_hidden(document, &input) {
-            let username_input = input.clone().downcast::<DOMHTMLInputElement>();
-            if let Ok(username_input) = username_input {
-                username_input.set_value(username);
-                change_event(&input);
-                break;
+/// Get the element containing the login form of `element`: its <form> element or, for formless
+/// logins, its closest ancestor containing an element matched by `selector`.
+fn login_container(element: &DOMElement, selector: &str) -> DOMElement {
+    let mut formless_container = None;
+    let mut ancestor = element.parent_element();
+    while let Some(el) = ancestor {
+        if is_tag(&el, "FORM") {
+            return el;
+        }
+        if is_tag(&el, "BODY") {
+            break;
+        }
+        if formless_container.is_none() {
+            let contains_other_input = NodeIter::new(el.query_selector_all(selector).ok())
+                .any(|input| input != *element);
+            if contains_other_input {
+                formless_container = Some(el.clone());
             }
         }
+        ancestor = el.parent_element();
     }
+    formless_container
+        .or_else(|| element.owner_document().and_then(|document| document.body()).map(|body| body.upcast()))
+        .unwrap_or_else(|| element.clone())
 }
 
-/// Submit the login form.
-pub fn submit_login_form(document: &DOMDocument) {
-    let login_form = find_login_form(document);
-    if let Some(login_form) = login_form {
-        login_form.submit();
+/// Set the value of an input and trigger the events so that the JavaScript frameworks see it.
+/// Since the value is not set by the JavaScript setter, frameworks like React which track the
+/// value will notice the change when receiving the input event.
+fn set_value(input: &DOMHTMLInputElement, value: &str) {
+    input.set_value(value);
+    let element = input.clone().upcast();
+    input_event(&element);
+    change_event(&element);
+}
+
+/// Get the score of an input to be the username input: the higher, the more likely.
+fn username_score(document: &DOMDocument, input: &DOMElement) -> u32 {
+    let description = format!("{} {} {} {} {}", attribute(input, "name"), attribute(input, "id"),
+        attribute(input, "placeholder"), attribute(input, "aria-label"), label_text(document, input));
+    username_score_from_attributes(&attribute(input, "autocomplete"), &attribute(input, "type"), &description)
+}
+
+/// Get the score of an input from its autocomplete and type attributes, and the lowercase
+/// `description` made of its name, id, placeholder and labels.
+fn username_score_from_attributes(autocomplete: &str, input_type: &str, description: &str) -> u32 {
+    if autocomplete.split_whitespace().any(|token| token == "username" || token == "email") {
+        return 10;
+    }
+    if description.contains("search") {
+        return 0;
+    }
+    let mut score = 1;
+    if input_type == "email" {
+        score += 5;
+    }
+    if USERNAME_WORDS.iter().any(|word| description.contains(word)) {
+        score += 4;
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{USERNAME_ONLY_MIN_SCORE, is_submit_text, username_score_from_attributes};
+
+    #[test]
+    fn submit_text() {
+        assert!(is_submit_text("Sign in"));
+        assert!(is_submit_text("  LOG IN  "));
+        assert!(is_submit_text("Continue"));
+        assert!(is_submit_text("Se connecter"));
+        assert!(is_submit_text("Suivant"));
+        assert!(!is_submit_text("Cancel"));
+        assert!(!is_submit_text("Forgot your password?"));
+        assert!(!is_submit_text(""));
+    }
+
+    #[test]
+    fn username_score() {
+        // The autocomplete attribute wins over everything else.
+        assert_eq!(username_score_from_attributes("username", "text", ""), 10);
+        assert_eq!(username_score_from_attributes("section-login email", "text", "search"), 10);
+        assert_eq!(username_score_from_attributes("off", "email", "email"), 10);
+
+        assert_eq!(username_score_from_attributes("", "email", ""), 6);
+        assert_eq!(username_score_from_attributes("", "text", "user_name  your username"), 5);
+        assert_eq!(username_score_from_attributes("", "text", "identifiant   "), 5);
+        assert_eq!(username_score_from_attributes("", "text", "first_name   first name"), 1);
+
+        // Search inputs are never usernames, even when their description contains a username word.
+        assert_eq!(username_score_from_attributes("", "text", "q search  search users"), 0);
+        assert_eq!(username_score_from_attributes("", "email", "search"), 0);
+    }
+
+    #[test]
+    fn username_only_min_score() {
+        // Without a password input, only the inputs that really look like a username are used.
+        assert!(username_score_from_attributes("", "text", "first_name") < USERNAME_ONLY_MIN_SCORE);
+        assert!(username_score_from_attributes("", "text", "q") < USERNAME_ONLY_MIN_SCORE);
+        assert!(username_score_from_attributes("", "text", "login") >= USERNAME_ONLY_MIN_SCORE);
+        assert!(username_score_from_attributes("", "email", "") >= USERNAME_ONLY_MIN_SCORE);
+        assert!(username_score_from_attributes("username", "text", "") >= USERNAME_ONLY_MIN_SCORE);
     }
 }
@@ -37,15 +37,11 @@ use webkit2gtk_webextension::{
 
 use titanium_common::protocol::decode;
 
-use adblocker::Adblocker;
+use adblocker::{ADBLOCKER, request_type};
 use executor::{self, Executor};
-use executor::Msg::{DocumentLoaded, MessageRecv};
+use executor::Msg::{Blocked, DocumentLoaded, MessageRecv};
 use self::Msg::*;
 
-thread_local! {
-    static ADBLOCKER: Adblocker = Adblocker::new();
-}
-
 pub struct MessageClient {
     model: Model,
 }
@@ -75,10 +71,11 @@ impl Update for MessageClient {
     fn update(&mut self, event: Msg) {
         match event {
             PageCreated(page) => {
-                // TODO: this should be disconnected later somehow.
-                connect!(self.model.relm, page, connect_send_request(_, request, _),
-                    return block_request(request));
                 let executor = execute::<Executor>(page.clone());
+                let stream = executor.clone();
+                // TODO: this should be disconnected later somehow.
+                connect!(self.model.relm, page, connect_send_request(page, request, _),
+                    return block_request(&stream, page, request));
                 connect_stream!(page, connect_document_loaded(_), executor, DocumentLoaded);
                 connect_stream!(return executor, page, connect_user_message_received(_, msg), (message_recv(msg), true));
                 self.model.executors.push(executor);
@@ -112,9 +109,15 @@ impl MessageClient {
     }
 }
 
-fn block_request(request: &URIRequest) -> bool {
+fn block_request(executor: &EventStream<executor::Msg>, page: &WebPage, request: &URIRequest) -> bool {
     if let Some(url) = request.uri() {
-        return ADBLOCKER.with(|adblocker| adblocker.should_block(&url));
+        let source_url = page.uri().map(|url| url.to_string()).unwrap_or_default();
+        let request_type = request_type(&url, &source_url);
+        let rule = ADBLOCKER.with(|adblocker| adblocker.blocking_rule(&url, &source_url, request_type));
+        if let Some(rule) = rule {
+            executor.emit(Blocked(url.to_string(), rule, request_type == "document"));
+            return true;
+        }
     }
     false
 }